@@ -10,5 +10,19 @@ pub const KERNEL_HEAP_SIZE: usize = 0x800000;
 pub const KERNEL_STACK_SIZE: usize = 0x80000;
 pub const USER_STACK_SIZE: usize = 0x80000;
 pub const USER_STACK_OFFSET: usize = 0xffffffff00000000;
+// exec 时参数与环境变量的总大小（包括字符串指针）的上限
+// 与 Linux 相同，为用户栈大小的 1/4 ，保证它们总能放在用户栈上
+pub const ARG_MAX: usize = USER_STACK_SIZE / 4;
+// 位置无关的可执行文件 (PIE) 被加载到的基址
+pub const USER_PIE_BASE: usize = 0x40000000;
+// mmap 未指定地址时，从这里开始寻找空闲的虚拟地址区间
+pub const USER_MMAP_OFFSET: usize = 0x1000000000;
 
-pub const PAGE_SIZE: usize = 4096;
\ No newline at end of file
+pub const PAGE_SIZE: usize = 4096;
+
+// 交换区的大小
+pub const SWAP_SIZE: usize = 0x800000;
+// 驻留在内存中的可换出的用户页超过这个数目，或者剩余的物理页帧少于 SWAP_MIN_FREE_FRAMES 时
+// 在分配新的物理页帧前先换出一页
+pub const SWAP_MAX_RESIDENT_PAGES: usize = 1024;
+pub const SWAP_MIN_FREE_FRAMES: usize = 64;
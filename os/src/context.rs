@@ -6,7 +6,7 @@ use riscv::register::sstatus;
 use core::mem::zeroed;
 
 #[repr(C)]      // Disable struct reposition
-// #[derive(Debug)]
+#[derive(Clone)]
 pub struct TrapFrame {
     pub x: [usize; 32],     // General registers
     pub sstatus: Sstatus,   // Supervisor Status Register
@@ -78,6 +78,24 @@ impl ContextContent {
             }
         }
     }
+    // 为 fork 出的新线程构造栈上的初始状态信息
+    // 直接复制父线程进入内核时保存的 TrapFrame
+    // 只是将返回值 a0 修改为 0 ，使子线程从 fork 返回 0
+    fn new_fork(
+        tf: &TrapFrame,
+        satp: usize
+        ) -> Self {
+        ContextContent {
+            ra: __trapret as usize,
+            satp,
+            s: [0; 12],
+            tf: {
+                let mut tf = tf.clone();
+                tf.x[10] = 0;
+                tf
+            }
+        }
+    }
     // 将自身压到栈上，并返回 Context
     unsafe fn push_at(self, stack_top: usize) -> Context {
         let ptr = (stack_top as *mut ContextContent).sub(1);
@@ -117,6 +135,14 @@ impl Context {
         // 压到内核栈
         ContextContent::new_user_thread(entry, ustack_top, satp).push_at(kstack_top)
     }
+    pub unsafe fn new_fork(
+        tf: &TrapFrame,
+        kstack_top: usize,
+        satp: usize
+        ) -> Context {
+        // 同样压到新线程的内核栈
+        ContextContent::new_fork(tf, satp).push_at(kstack_top)
+    }
     pub unsafe fn append_initial_arguments(&self, args: [usize; 3]) {
         let context_content = &mut *(self.content_addr as *mut ContextContent);
         context_content.tf.x[10] = args[0];
@@ -0,0 +1,19 @@
+use alloc::sync::Arc;
+use rcore_fs::vfs::INode;
+
+// 进程打开的文件，保存在进程的文件描述符表中
+#[derive(Clone)]
+pub enum File {
+    // 标准输入：从串口读入字符
+    // nonblock 为 true 时，没有已输入的字符也不会等待
+    Stdin {
+        nonblock: bool,
+    },
+    // 标准输出：向串口输出字符
+    Stdout,
+    // 文件系统中的文件，offset 为下一次读写的位置
+    INode {
+        inode: Arc<dyn INode>,
+        offset: usize,
+    },
+}
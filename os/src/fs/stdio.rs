@@ -30,6 +30,11 @@ impl Stdin {
         self.pushed.notify();
     }
 
+    // 消费者：取出一个已经输入的字符，队列为空时返回 None 而不等待
+    pub fn try_pop(&self) -> Option<char> {
+        self.buf.lock().pop_front()
+    }
+
     // 消费者：取出字符
     // 运行在请求字符输入的线程上
     pub fn pop(&self) -> char {
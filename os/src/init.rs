@@ -1,7 +1,10 @@
 use crate::consts::*;
 use crate::memory::{
     alloc_frame,
-    dealloc_frame
+    dealloc_frame,
+    alloc_frames,
+    dealloc_frames,
+    free_frame_count
 };
 
 global_asm!(include_str!("boot/entry64.asm"));
@@ -18,8 +21,11 @@ pub extern "C" fn rust_main() -> ! {
         ((end as usize - KERNEL_BEGIN_VADDR + KERNEL_BEGIN_PADDR) >> 12) + 1,
         PHYSICAL_MEMORY_END >> 12
     );
+    contiguous_frame_allocating_test();
     crate::interrupt::init();
     crate::fs::init();
+    // 创建用户线程前，先检查销毁用户线程时物理页帧是否全部被回收
+    user_memory_teardown_test();
     crate::process::init();
     crate::timer::init();
     crate::process::run();
@@ -37,6 +43,25 @@ fn frame_allocating_test() {
     println!("alloc {:x?}", alloc_frame());
 }
 
+// 分配物理地址连续的多个物理页帧
+fn contiguous_frame_allocating_test() {
+    let before = free_frame_count();
+    // 先分配一个物理页帧，使得接下来的分配需要跳过一些物理页帧才能满足对齐要求
+    let single = alloc_frame().unwrap();
+    let frames = alloc_frames(8, 8).unwrap();
+    println!("alloc 8 frames at {:x?}", frames);
+    assert!(frames.number() % 8 == 0);
+    // 对齐时跳过的物理页帧仍然可以被分配
+    let f = alloc_frame().unwrap();
+    println!("alloc {:x?}", f);
+    assert!(f.number() < frames.number() || frames.number() == single.number() + 1);
+    dealloc_frame(f);
+    dealloc_frames(frames, 8);
+    dealloc_frame(single);
+    assert!(free_frame_count() == before);
+    println!("contiguous frame allocating test passed!");
+}
+
 fn dynamic_allocating_test() {
     use alloc::vec::Vec;
     use alloc::boxed::Box;
@@ -94,4 +119,25 @@ fn execute_unexecutable_test() {
 // 找不到页表项
 fn read_invalid_test() {
     println!("{}", unsafe { *(0x12345678 as usize as *const u8) });
+}
+
+// 反复创建并销毁用户线程
+// 其虚拟内存空间所用的物理页帧（包括页表）应当全部被回收
+fn user_memory_teardown_test() {
+    use alloc::vec::Vec;
+    use crate::fs::{ ROOT_INODE, INodeExt };
+    use crate::process::structs::Thread;
+
+    let data = ROOT_INODE
+        .lookup("rust/hello_world")
+        .unwrap()
+        .read_as_vec()
+        .unwrap();
+    let before = free_frame_count();
+    for _ in 0..10 {
+        let thread = unsafe { Thread::new_user(data.as_slice(), Vec::new(), Vec::new()) }.unwrap();
+        drop(thread);
+    }
+    assert!(free_frame_count() == before);
+    println!("free frames: {}, user memory teardown assertion successfully!", before);
 }
\ No newline at end of file
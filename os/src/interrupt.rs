@@ -13,7 +13,8 @@ use riscv::register::{
 };
 use crate::timer::{
     TICKS,
-    clock_set_next_event
+    clock_set_next_event,
+    wake_up_sleepers
 };
 use crate::context::TrapFrame;
 use crate::process::tick;
@@ -72,6 +73,16 @@ fn breakpoint(sepc: &mut usize) {
 }
 
 fn page_fault(tf: &mut TrapFrame) {
+    // 先交给当前线程的虚拟内存空间尝试处理，例如写时复制、第一次访问时分配物理页帧
+    if crate::process::handle_page_fault(tf.stval) {
+        return;
+    }
+    // 在内核中通过 copy_from_user 等函数访问用户内存时出错
+    // 跳转到其错误处理代码，使系统调用返回 -EFAULT
+    if let Some(fixup) = crate::memory::user::fixup(tf.sepc) {
+        tf.sepc = fixup;
+        return;
+    }
     println!("{:?} va = {:#x} instruction = {:#x}", tf.scause.cause(), tf.stval, tf.sepc);
     panic!("page fault!");
 }
@@ -79,6 +90,8 @@ fn page_fault(tf: &mut TrapFrame) {
 fn super_timer() {
     // 设置下一次时钟中断触发时间
     clock_set_next_event();
+    // 唤醒睡眠时间已到的线程
+    wake_up_sleepers();
     tick();
     unsafe {
         // 更新时钟中断触发计数
@@ -126,7 +139,7 @@ fn syscall(tf: &mut TrapFrame) {
     tf.sepc += 4;
     let ret = crate::syscall::syscall(
         tf.x[17],
-        [tf.x[10], tf.x[11], tf.x[12]],
+        [tf.x[10], tf.x[11], tf.x[12], tf.x[13], tf.x[14], tf.x[15]],
         tf
     );
     tf.x[10] = ret as usize;
@@ -17,6 +17,7 @@ mod consts;
 mod memory;
 mod process;
 mod syscall;
+mod strace;
 mod fs;
 mod sync;
 
@@ -1,64 +1,180 @@
 use crate::consts::MAX_PHYSICAL_PAGES;
 use spin::Mutex;
 
+// 线段树的每个节点记录其所管辖的物理页区间中
+// 最长的连续空闲物理页数 max ，以及从左端开始、到右端结束的连续空闲物理页数 pre, suf
+// 借此可以快速找到足够长的连续空闲物理页
 pub struct SegmentTreeAllocator {
-    a: [u8; MAX_PHYSICAL_PAGES << 1],
+    max: [u16; MAX_PHYSICAL_PAGES << 1],
+    pre: [u16; MAX_PHYSICAL_PAGES << 1],
+    suf: [u16; MAX_PHYSICAL_PAGES << 1],
+    // 叶子节点数，为 2 的幂
     m: usize,
+    // 管理的物理页数
     n: usize,
-    offset: usize
+    // 第 i 个叶子节点对应的物理页号为 offset + i
+    offset: usize,
+    // 剩余的空闲物理页数
+    free: usize,
 }
 
 pub static SEGMENT_TREE_ALLOCATOR: Mutex<SegmentTreeAllocator> = Mutex::new(SegmentTreeAllocator {
-    a: [0; MAX_PHYSICAL_PAGES << 1],
+    max: [0; MAX_PHYSICAL_PAGES << 1],
+    pre: [0; MAX_PHYSICAL_PAGES << 1],
+    suf: [0; MAX_PHYSICAL_PAGES << 1],
     m: 0,
     n: 0,
-    offset: 0
+    offset: 0,
+    free: 0,
 });
 
 impl SegmentTreeAllocator {
     // 使用物理页号区间 [l,r) 进行初始化
     pub fn init(&mut self, l: usize, r: usize) {
-        self.offset = l - 1;
+        self.offset = l;
         self.n = r - l;
         self.m = 1;
-        while self.m < self.n + 2 {
+        while self.m < self.n {
             self.m = self.m << 1;
         }
-        for i in 1..(self.m << 1) { self.a[i] = 1; }
-        for i in 1..self.n { self.a[self.m + i] = 0; }
-        for i in (1..self.m).rev() { self.a[i] = self.a[i << 1] & self.a[(i << 1) | 1]; }
+        // 超出 n 的叶子节点视为已被占用
+        for i in 0..self.m {
+            let free = if i < self.n { 1 } else { 0 };
+            self.max[self.m + i] = free;
+            self.pre[self.m + i] = free;
+            self.suf[self.m + i] = free;
+        }
+        let mut len = 1;
+        let mut level = self.m >> 1;
+        while level > 0 {
+            for p in level..(level << 1) { self.pull(p, len); }
+            level >>= 1;
+            len <<= 1;
+        }
+        self.free = self.n;
+    }
+    // 由两个子节点更新节点 p ，其子节点管辖的区间长度均为 half
+    fn pull(&mut self, p: usize, half: usize) {
+        let (l, r) = (p << 1, (p << 1) | 1);
+        self.max[p] = self.max[l]
+            .max(self.max[r])
+            .max(self.suf[l] + self.pre[r]);
+        self.pre[p] = if self.pre[l] as usize == half { self.pre[l] + self.pre[r] } else { self.pre[l] };
+        self.suf[p] = if self.suf[r] as usize == half { self.suf[r] + self.suf[l] } else { self.suf[r] };
+    }
+    // 将第 [start, start + count) 个叶子节点设为空闲或占用，并自下而上进行更新
+    fn set_range(&mut self, start: usize, count: usize, free: bool) {
+        for i in start..start + count {
+            let value = if free { 1 } else { 0 };
+            self.max[self.m + i] = value;
+            self.pre[self.m + i] = value;
+            self.suf[self.m + i] = value;
+        }
+        // 逐层更新覆盖了这些叶子节点的祖先
+        let (mut l, mut r) = ((self.m + start) >> 1, (self.m + start + count - 1) >> 1);
+        let mut half = 1;
+        while l > 0 {
+            for p in l..=r { self.pull(p, half); }
+            l >>= 1;
+            r >>= 1;
+            half <<= 1;
+        }
     }
-    // 分配一个物理页
-    // 自上而下寻找可用的最小物理页号
-    // 注意，我们假定永远不会出现物理页耗尽的情况
-    pub fn alloc(&mut self) -> usize {
-        // assume that we never run out of physical memory
-        if self.a[1] == 1 {
-            panic!("physical memory depleted!");
+    // 在节点 p 管辖的区间（起始于第 left 个叶子节点，长为 len ）中
+    // 寻找最靠左的起始位置 s ，使得 [s, s + count) 均为空闲，且 s 对应的物理页号是 align 的倍数
+    // 跨越区间中点的空闲区间只在此处考虑其位于区间内的部分，更长的部分由祖先节点考虑
+    fn find(&self, p: usize, left: usize, len: usize, count: usize, align: usize) -> Option<usize> {
+        if (self.max[p] as usize) < count {
+            return None;
+        }
+        if len == 1 {
+            return if (self.offset + left) % align == 0 { Some(left) } else { None };
         }
-        let mut p = 1;
-        while p < self.m {
-            if self.a[p << 1] == 0 { p = p << 1; } else { p = (p << 1) | 1; }
+        let half = len >> 1;
+        let (l, r) = (p << 1, (p << 1) | 1);
+        // 先找左半部分
+        if let Some(s) = self.find(l, left, half, count, align) {
+            return Some(s);
         }
-        let result = p + self.offset - self.m;
-        self.a[p] = 1;
-        p >>= 1;
-        while p > 0 {
-            self.a[p] = self.a[p << 1] & self.a[(p << 1) | 1];
-            p >>= 1;
+        // 再找跨越中点的空闲区间
+        let mid = left + half;
+        if self.suf[l] as usize + self.pre[r] as usize >= count {
+            let start = mid - self.suf[l] as usize;
+            // 向上对齐到 align
+            let s = ((self.offset + start + align - 1) / align * align) - self.offset;
+            if s + count <= mid + self.pre[r] as usize {
+                return Some(s);
+            }
         }
-        result
+        // 最后找右半部分
+        self.find(r, mid, half, count, align)
+    }
+    // 分配 count 个连续的物理页，其起始物理页号为 align 的倍数
+    // 返回起始物理页号，找不到足够长的连续空闲物理页时返回 None
+    pub fn alloc_contiguous(&mut self, count: usize, align: usize) -> Option<usize> {
+        assert!(count > 0 && align > 0, "invalid count or align!");
+        let start = self.find(1, 0, self.m, count, align)?;
+        self.set_range(start, count, false);
+        self.free -= count;
+        Some(start + self.offset)
+    }
+    // 回收从物理页号 start 开始的 count 个连续的物理页
+    pub fn dealloc_contiguous(&mut self, start: usize, count: usize) {
+        let start = start - self.offset;
+        assert!(start + count <= self.n, "dealloc out of range!");
+        for i in start..start + count {
+            assert!(self.max[self.m + i] == 0, "dealloc a free frame!");
+        }
+        self.set_range(start, count, true);
+        self.free += count;
+    }
+    // 分配一个物理页，返回物理页号
+    // 总是找到可用的最小物理页号
+    pub fn alloc(&mut self) -> Option<usize> {
+        self.alloc_contiguous(1, 1)
+    }
+    // 当前剩余的可用物理页数
+    pub fn free_count(&self) -> usize {
+        self.free
     }
     // 回收物理页号为 n 的物理页
-    // 自下而上进行更新
     pub fn dealloc(&mut self, n: usize) {
-        let mut p = n + self.m - self.offset;
-        assert!(self.a[p] == 1);
-        self.a[p] = 0;
-        p >>= 1;
-        while p > 0 {
-            self.a[p] = self.a[p << 1] & self.a[(p << 1) | 1];
-            p >>= 1;
-        }
+        self.dealloc_contiguous(n, 1)
+    }
+}
+
+// 物理页帧的引用计数
+// 写时复制时多个页表会同时映射到同一个物理页帧
+// 只有当引用计数减为 0 时才能将其回收
+pub struct FrameRefCount {
+    a: [u16; MAX_PHYSICAL_PAGES],
+    offset: usize
+}
+
+pub static FRAME_REF_COUNT: Mutex<FrameRefCount> = Mutex::new(FrameRefCount {
+    a: [0; MAX_PHYSICAL_PAGES],
+    offset: 0
+});
+
+impl FrameRefCount {
+    // 与 SegmentTreeAllocator 一样，使用物理页号区间 [l,r) 进行初始化
+    pub fn init(&mut self, l: usize, r: usize) {
+        self.offset = l;
+        for i in 0..(r - l) { self.a[i] = 0; }
+    }
+    // 物理页号为 n 的物理页帧当前的引用计数
+    pub fn get(&self, n: usize) -> usize {
+        self.a[n - self.offset] as usize
+    }
+    // 引用计数 +1 ，返回新的引用计数
+    pub fn increase(&mut self, n: usize) -> usize {
+        self.a[n - self.offset] += 1;
+        self.a[n - self.offset] as usize
+    }
+    // 引用计数 -1 ，返回新的引用计数
+    pub fn decrease(&mut self, n: usize) -> usize {
+        assert!(self.a[n - self.offset] > 0);
+        self.a[n - self.offset] -= 1;
+        self.a[n - self.offset] as usize
     }
 }
\ No newline at end of file
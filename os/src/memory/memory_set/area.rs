@@ -24,11 +24,74 @@ impl MemoryArea {
             self.handler.map(pt, page, &self.attr);
         }
     }
-    fn unmap(&self, pt : &mut PageTableImpl) {
+    pub fn unmap(&self, pt : &mut PageTableImpl) {
         for page in PageRange::new(self.start, self.end) {
             self.handler.unmap(pt, page);
         }
     }
+    // 在新页表 pt 中复制本区间在原页表 src_pt 中的全部映射
+    pub fn clone_map(&self, pt: &mut PageTableImpl, src_pt: &mut PageTableImpl) {
+        for page in PageRange::new(self.start, self.end) {
+            self.handler.clone_map(pt, src_pt, page, &self.attr);
+        }
+    }
+    // 将本区间的结束地址调整为 end
+    // 变长时为新增的虚拟页插入映射，变短时删除多出的虚拟页的映射
+    pub fn resize(&mut self, pt: &mut PageTableImpl, end: usize) {
+        if end > self.end {
+            for page in PageRange::new(self.end, end) {
+                self.handler.map(pt, page, &self.attr);
+            }
+        } else {
+            for page in PageRange::new(end, self.end) {
+                self.handler.unmap(pt, page);
+            }
+        }
+        self.end = end;
+    }
+    // 删除本区间中 [start, end) 部分的映射
+    pub fn unmap_range(&self, pt: &mut PageTableImpl, start: usize, end: usize) {
+        for page in PageRange::new(start, end) {
+            self.handler.unmap(pt, page);
+        }
+    }
+    // 取出本区间中 [start, end) 的部分作为一个新的区间，使用相同的 handler 以及 attr
+    // 不修改页表中的映射
+    pub fn sub_area(&self, start: usize, end: usize) -> MemoryArea {
+        MemoryArea::new(start, end, self.handler.clone(), self.attr.clone())
+    }
+    // 将本区间的权限修改为 attr ，并应用到区间内每个虚拟页的页表项上
+    pub fn protect(&mut self, pt: &mut PageTableImpl, attr: MemoryAttr) {
+        for page in PageRange::new(self.start, self.end) {
+            self.handler.protect(pt, page, &attr);
+        }
+        self.attr = attr;
+    }
+    // 本区间的权限
+    pub fn attr(&self) -> &MemoryAttr {
+        &self.attr
+    }
+    // 本区间的起始地址
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    // 本区间的结束地址
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    // 虚拟地址 va 所在的虚拟页是否与本区间相交
+    // 与 is_overlap_with 一样以页为单位：区间的起止地址不必页对齐，但映射总是覆盖整页
+    pub fn contains(&self, va: usize) -> bool {
+        let page = va / PAGE_SIZE;
+        self.start < self.end
+            && page >= self.start / PAGE_SIZE
+            && page <= (self.end - 1) / PAGE_SIZE
+    }
+    // 访问本区间内的虚拟地址 va 时发生缺页异常，交给 handler 处理
+    // 返回值表示异常是否被成功处理
+    pub fn handle_page_fault(&self, pt: &mut PageTableImpl, va: usize) -> bool {
+        self.handler.handle_page_fault(pt, va, &self.attr)
+    }
     // 是否与另一虚拟地址区间相交
     pub fn is_overlap_with(&self, start_addr : usize, end_addr : usize) -> bool {
         let p1 = self.start / PAGE_SIZE;
@@ -46,19 +109,21 @@ impl MemoryArea {
             attr : attr,
         }
     }
+    // 将从 src 开始的 length 字节复制到本区间的起始地址处，区间内的其余部分清零
+    // 起始地址不必页对齐，第一页中起始地址之前的部分同样被清零
     pub fn page_copy(&self, pt: &mut PageTableImpl, src: usize, length: usize) {
-        let mut l = length;
-        let mut s = src;
+        let mut copied = 0;
         for page in PageRange::new(self.start, self.end) {
+            // 本区间在这一页中开始的位置
+            let va = self.start.max(page);
+            let len = (page + PAGE_SIZE - va).min(length - copied);
+            // 按需分配物理页帧的区间（如 Lazy）中，这一页可能还没有映射
+            if pt.get_entry(page).map_or(true, |entry| entry.is_unused() || entry.swap_slot().is_some()) {
+                self.handler.handle_page_fault(pt, page, &self.attr);
+            }
             // 交给 MemoryHandler 逐页进行复制
-            self.handler.page_copy(
-                pt,
-                page,
-                s,
-                if l < PAGE_SIZE { l } else { PAGE_SIZE },
-            );
-            s += PAGE_SIZE;
-            if l >= PAGE_SIZE { l -= PAGE_SIZE; }
+            self.handler.page_copy(pt, va, src + copied, len);
+            copied += len;
         }
     }
 }
\ No newline at end of file
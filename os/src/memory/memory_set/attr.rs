@@ -5,6 +5,7 @@ pub struct MemoryAttr {
     user : bool,    // 用户态是否可访问
     readonly : bool,    // 是否只读
     execute : bool,      // 是否可执行
+    no_access : bool,   // 是否完全不可访问，如 PROT_NONE 的保护页
 }
 
 impl MemoryAttr {
@@ -14,6 +15,7 @@ impl MemoryAttr {
             user : false,
             readonly : false,
             execute : false,
+            no_access : false,
         }
     }
     // 根据要求修改所需权限
@@ -26,9 +28,39 @@ impl MemoryAttr {
     pub fn set_execute(mut self) -> Self {
         self.execute = true;   self
     }
+    pub fn set_no_access(mut self) -> Self {
+        self.no_access = true;   self
+    }
+    // 该权限是否允许用户态访问
+    pub fn is_user(&self) -> bool {
+        self.user
+    }
+    // 该权限是否允许读取
+    pub fn readable(&self) -> bool {
+        !self.no_access
+    }
+    // 该权限是否允许写入
+    pub fn writable(&self) -> bool {
+        !self.readonly && !self.no_access
+    }
+    // 该权限是否允许执行
+    pub fn executable(&self) -> bool {
+        self.execute && !self.no_access
+    }
+    // 同时包含两者的权限：其中任意一个允许的访问都被允许
+    pub fn union(&self, other: &MemoryAttr) -> MemoryAttr {
+        MemoryAttr {
+            user : self.user || other.user,
+            readonly : self.readonly && other.readonly,
+            execute : self.execute || other.execute,
+            no_access : self.no_access && other.no_access,
+        }
+    }
     // 根据设置的权限要求修改页表项
     pub fn apply(&self, entry : &mut PageEntry) {
-        entry.set_present(true);    // 设置页表项存在
+        // 设置页表项存在
+        // 完全不可访问的页仍保留映射目标，只是将页表项设为不存在，访问时触发缺页异常
+        entry.set_present(!self.no_access);
         entry.set_user(self.user);  // 设置用户态访问权限
         entry.set_writable(!self.readonly); //设置写权限
         entry.set_execute(self.execute); //设置可执行权限
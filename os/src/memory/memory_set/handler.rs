@@ -1,9 +1,18 @@
 use crate::memory::paging::PageTableImpl;
 use super::attr::MemoryAttr;
-use crate::memory::alloc_frame; 
-use core::fmt::Debug;
-use alloc::boxed::Box;
+use crate::memory::{
+    alloc_frame,
+    dealloc_frame,
+    frame_ref_count,
+    frame_ref_increase,
+    frame_ref_decrease,
+};
+use riscv::addr::{ Frame, PhysAddr };
+use core::fmt::{ self, Debug, Formatter };
+use alloc::{ boxed::Box, sync::Arc };
+use rcore_fs::vfs::INode;
 use crate::memory::access_pa_via_va;
+use crate::memory::swap;
 use crate::consts::PAGE_SIZE;
 
 // 定义 MemoryHandler trait
@@ -13,7 +22,27 @@ pub trait MemoryHandler: Debug + 'static {
     // 注意 map 并没有 pa 作为参数，因此接口实现者要给出该虚拟页要映射到哪个物理页
     fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr);
     fn unmap(&self, pt: &mut PageTableImpl, va: usize);
+    // 将从 src 开始的 length 字节复制到 va 处，va 所在虚拟页的其余部分清零
+    // va 不必页对齐，但复制的内容不能超出 va 所在的虚拟页
     fn page_copy(&self, pt: &mut PageTableImpl, va: usize, src: usize, length: usize);
+    // 复制虚拟内存空间时使用：在新页表 pt 中为虚拟页 va 建立映射
+    // 其内容与原页表 src_pt 中同一虚拟页的内容相同
+    fn clone_map(&self, pt: &mut PageTableImpl, src_pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr);
+    // 访问虚拟页 va 时发生缺页异常，attr 为该页所在区间的权限
+    // 返回值表示该异常是否被成功处理，默认无法处理
+    fn handle_page_fault(&self, _pt: &mut PageTableImpl, _va: usize, _attr: &MemoryAttr) -> bool {
+        false
+    }
+    // 修改所在区间的权限时使用：将新的权限 attr 应用到虚拟页 va 的页表项上
+    // 默认对已经建立了映射的页直接应用 attr ，并刷新 TLB
+    fn protect(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        if let Some(entry) = pt.get_entry(va) {
+            if !entry.is_unused() {
+                attr.apply(entry);
+                entry.update();
+            }
+        }
+    }
 }
 impl Clone for Box<dyn MemoryHandler> {
     fn clone(&self) -> Box<dyn MemoryHandler> { self.box_clone() }
@@ -35,27 +64,34 @@ impl MemoryHandler for Linear {
         attr.apply(pt.map(va, va - self.offset));
     }
     fn unmap(&self, pt: &mut PageTableImpl, va: usize) { pt.unmap(va); }
+    fn clone_map(&self, pt: &mut PageTableImpl, _src_pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        // 线性映射的目标是固定的，直接映射即可
+        self.map(pt, va, attr);
+    }
     fn page_copy(&self, pt: &mut PageTableImpl, va: usize, src: usize, length: usize) {
         let pa = pt.get_entry(va)
             .expect("get pa error!")
             .0
             .addr()
             .as_usize();
-        assert!(va == access_pa_via_va(pa));
-        assert!(va == pa + self.offset);
+        let offset = va % PAGE_SIZE;
+        let page = va - offset;
+        assert!(page == access_pa_via_va(pa));
+        assert!(page == pa + self.offset);
         unsafe {
             let dst = core::slice::from_raw_parts_mut(
-                va as *mut u8,
+                page as *mut u8,
                 PAGE_SIZE,
             );
+            for i in 0..offset { dst[i] = 0; }
             if length > 0 {
                 let src = core::slice::from_raw_parts(
                     src as *const u8,
-                    PAGE_SIZE,
+                    length,
                 );
-                for i in 0..length { dst[i] = src[i]; }
+                for i in 0..length { dst[offset + i] = src[i]; }
             }
-            for i in length..PAGE_SIZE { dst[i] = 0; }
+            for i in offset + length..PAGE_SIZE { dst[i] = 0; }
         }
     }
 }
@@ -77,7 +113,27 @@ impl MemoryHandler for ByFrame {
         attr.apply(pt.map(va, pa));
     }
     fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        // 删除映射的同时回收当初分配的物理页帧
+        let pa = pt.get_entry(va).expect("get pa error!").target();
         pt.unmap(va);
+        dealloc_frame(Frame::of_addr(PhysAddr::new(pa)));
+    }
+    fn clone_map(&self, pt: &mut PageTableImpl, src_pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        // 分配一个新的物理页帧，并将原物理页帧的内容整页复制过去
+        self.map(pt, va, attr);
+        let src_pa = src_pt.get_entry(va).expect("get src pa error!").target();
+        let dst_pa = pt.get_entry(va).expect("get dst pa error!").target();
+        unsafe {
+            let dst = core::slice::from_raw_parts_mut(
+                access_pa_via_va(dst_pa) as *mut u8,
+                PAGE_SIZE,
+            );
+            let src = core::slice::from_raw_parts(
+                access_pa_via_va(src_pa) as *const u8,
+                PAGE_SIZE,
+            );
+            dst.copy_from_slice(src);
+        }
     }
     fn page_copy(&self, pt: &mut PageTableImpl, va: usize, src: usize, length: usize) {
         let pa = pt.get_entry(va)
@@ -85,19 +141,330 @@ impl MemoryHandler for ByFrame {
             .0
             .addr()
             .as_usize();
+        // va 在所在虚拟页中的偏移
+        let offset = va % PAGE_SIZE;
         unsafe {
             let dst = core::slice::from_raw_parts_mut(
                 access_pa_via_va(pa) as *mut u8,
                 PAGE_SIZE,
             );
+            for i in 0..offset { dst[i] = 0; }
             if length > 0 {
                 let src = core::slice::from_raw_parts(
                     src as *const u8,
+                    length,
+                );
+                for i in 0..length { dst[offset + i] = src[i]; }
+            }
+            for i in offset + length..PAGE_SIZE { dst[i] = 0; }
+        }
+    }
+}
+// CopyOnWrite: 同 ByFrame 一样为每个虚拟页分配一个新的物理页帧
+// 但复制虚拟内存空间时并不复制物理页帧的内容，而是让新旧页表共享同一个物理页帧，并将其设为只读
+// 等到某一方试图写入而触发 StorePageFault 时，才为它复制一份
+#[derive(Debug, Clone)]
+pub struct CopyOnWrite;
+impl CopyOnWrite {
+    pub fn new() -> Self { CopyOnWrite {} }
+}
+impl MemoryHandler for CopyOnWrite {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        let frame = alloc_frame().expect("alloc_frame failed!");
+        frame_ref_increase(&frame);
+        let pa = frame.start_address().as_usize();
+        attr.apply(pt.map(va, pa));
+    }
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        // 还未建立映射的页（见 Lazy）不需要处理
+        let pa = match pt.get_entry(va) {
+            Some(entry) if !entry.is_unused() => entry.target(),
+            _ => return,
+        };
+        pt.unmap(va);
+        // 最后一个引用者负责回收物理页帧
+        let frame = Frame::of_addr(PhysAddr::new(pa));
+        if frame_ref_decrease(&frame) == 0 {
+            dealloc_frame(frame);
+        }
+    }
+    fn page_copy(&self, pt: &mut PageTableImpl, va: usize, src: usize, length: usize) {
+        // 刚刚 map 的物理页帧尚未被共享，与 ByFrame 一样直接复制
+        ByFrame::new().page_copy(pt, va, src, length);
+    }
+    fn clone_map(&self, pt: &mut PageTableImpl, src_pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        // 原页表中的映射也要设为只读，这样双方写入时都会触发异常
+        let src_entry = match src_pt.get_entry(va) {
+            Some(entry) if !entry.is_unused() => entry,
+            _ => return,
+        };
+        let pa = src_entry.target();
+        src_entry.set_writable(false);
+        src_entry.update();
+        // 新页表映射到同一个物理页帧
+        let entry = pt.map(va, pa);
+        attr.apply(entry);
+        entry.set_writable(false);
+        frame_ref_increase(&Frame::of_addr(PhysAddr::new(pa)));
+    }
+    fn handle_page_fault(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> bool {
+        // 只有本应可写、但因共享而被设为只读的页才由我们处理
+        if !attr.writable() {
+            return false;
+        }
+        let entry = match pt.get_entry(va) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        if !entry.present() || entry.writable() {
+            return false;
+        }
+        let pa = entry.target();
+        let frame = Frame::of_addr(PhysAddr::new(pa));
+        if frame_ref_count(&frame) > 1 {
+            // 仍有其他页表在共享这个物理页帧
+            // 分配一个新的物理页帧，将内容复制过去，并改为映射到新的物理页帧
+            let new_frame = alloc_frame().expect("alloc_frame failed!");
+            let new_pa = new_frame.start_address().as_usize();
+            unsafe {
+                let dst = core::slice::from_raw_parts_mut(
+                    access_pa_via_va(new_pa) as *mut u8,
+                    PAGE_SIZE,
+                );
+                let src = core::slice::from_raw_parts(
+                    access_pa_via_va(pa) as *const u8,
+                    PAGE_SIZE,
+                );
+                dst.copy_from_slice(src);
+            }
+            frame_ref_increase(&new_frame);
+            frame_ref_decrease(&frame);
+            entry.set_target(new_pa);
+        }
+        // 否则只剩下自己在使用，直接恢复写权限即可
+        entry.set_writable(true);
+        entry.update();
+        true
+    }
+    fn protect(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        let entry = match pt.get_entry(va) {
+            Some(entry) if !entry.is_unused() => entry,
+            _ => return,
+        };
+        attr.apply(entry);
+        // 仍被共享的物理页帧必须保持只读，写入时才能触发异常进行复制
+        if frame_ref_count(&Frame::of_addr(PhysAddr::new(entry.target()))) > 1 {
+            entry.set_writable(false);
+        }
+        entry.update();
+    }
+}
+
+// Lazy: map 时并不分配物理页帧，等到第一次访问某一页触发缺页异常时，才分配一个清零的物理页帧
+// 已经分配了物理页帧的页与 CopyOnWrite 的行为相同，复制虚拟内存空间时同样共享物理页帧
+// 适用于用户栈、用户堆等一开始内容全为 0 ，又不一定会被全部用到的区间
+// 这些页在物理页帧不足时可以被换出到交换区，再次访问时换入，见 memory::swap
+#[derive(Debug, Clone)]
+pub struct Lazy;
+impl Lazy {
+    pub fn new() -> Self { Lazy {} }
+}
+impl MemoryHandler for Lazy {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+    fn map(&self, _pt: &mut PageTableImpl, _va: usize, _attr: &MemoryAttr) {
+        // 什么都不做，等到访问时再分配
+    }
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        let token = pt.token();
+        let (mapped, slot) = match pt.get_entry(va) {
+            Some(entry) => (!entry.is_unused(), entry.swap_slot()),
+            None => (false, None),
+        };
+        match slot {
+            // 已被换出的页只需释放其在交换区中的位置
+            Some(slot) => {
+                swap::free_slot(slot);
+                pt.unmap(va);
+            },
+            None => {
+                if mapped {
+                    swap::untrack(token, va);
+                }
+                CopyOnWrite::new().unmap(pt, va);
+            },
+        }
+    }
+    fn page_copy(&self, pt: &mut PageTableImpl, va: usize, src: usize, length: usize) {
+        CopyOnWrite::new().page_copy(pt, va, src, length);
+    }
+    fn clone_map(&self, pt: &mut PageTableImpl, src_pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        // 原页表中已被换出的页先换入，再与新页表共享
+        let src_token = src_pt.token();
+        if let Some(entry) = src_pt.get_entry(va) {
+            if entry.swap_slot().is_some() {
+                swap::swap_in(src_token, entry, attr);
+            }
+        }
+        // 原页表中还未访问过的页，在新页表中同样等到访问时再分配
+        let mapped = src_pt.get_entry(va).map_or(false, |entry| !entry.is_unused());
+        if mapped {
+            CopyOnWrite::new().clone_map(pt, src_pt, va, attr);
+            swap::track(pt.token(), va);
+        }
+    }
+    fn handle_page_fault(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> bool {
+        // 完全不可访问的页不做处理
+        if !attr.readable() {
+            return false;
+        }
+        let token = pt.token();
+        let (mapped, swapped) = match pt.get_entry(va) {
+            Some(entry) => (!entry.is_unused(), entry.swap_slot().is_some()),
+            None => (false, false),
+        };
+        if swapped {
+            // 被换出的页，将其换入
+            swap::swap_in(token, pt.get_entry(va).unwrap(), attr);
+            return true;
+        }
+        if mapped {
+            // 已经分配了物理页帧，可能是写入被共享的页
+            return CopyOnWrite::new().handle_page_fault(pt, va, attr);
+        }
+        // 第一次访问，分配一个物理页帧并清零，必要时先换出一页
+        swap::reserve();
+        CopyOnWrite::new().map(pt, va, attr);
+        ByFrame::new().page_copy(pt, va, 0, 0);
+        swap::track(token, va);
+        true
+    }
+    fn protect(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        // 被换出的页在换入时才应用新的权限
+        let swapped = pt.get_entry(va).map_or(false, |entry| entry.swap_slot().is_some());
+        if !swapped {
+            CopyOnWrite::new().protect(pt, va, attr);
+        }
+    }
+}
+
+// FileBacked: 将文件 inode 中从 offset 开始的内容映射到从虚拟地址 start 开始的区间
+// map 时并不建立映射，等到第一次访问某一页触发缺页异常时，才分配物理页帧并从文件中读入其内容
+// shared 为 true 时，删除映射时会将被修改过的页写回文件；否则修改只对自己可见
+#[derive(Clone)]
+pub struct FileBacked {
+    inode: Arc<dyn INode>,
+    start: usize,
+    offset: usize,
+    shared: bool,
+}
+impl FileBacked {
+    pub fn new(inode: Arc<dyn INode>, start: usize, offset: usize, shared: bool) -> Self {
+        FileBacked { inode, start, offset, shared }
+    }
+    // 虚拟页 va 对应的内容在文件中的偏移量
+    // 由于使用的是整个映射的起始地址，拆分后的区间仍然可以共用同一个 FileBacked
+    fn file_offset(&self, va: usize) -> usize {
+        self.offset + (va & !(PAGE_SIZE - 1)) - self.start
+    }
+    // 将物理页帧 pa 中的内容写回文件中虚拟页 va 对应的位置，不会改变文件的大小
+    fn write_back(&self, va: usize, pa: usize) {
+        let offset = self.file_offset(va);
+        let size = self.inode.metadata().map(|metadata| metadata.size).unwrap_or(0);
+        if offset >= size {
+            return;
+        }
+        let length = PAGE_SIZE.min(size - offset);
+        let src = unsafe {
+            core::slice::from_raw_parts(access_pa_via_va(pa) as *const u8, length)
+        };
+        self.inode.write_at(offset, src).expect("failed to write back to file!");
+    }
+}
+impl Debug for FileBacked {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("FileBacked")
+            .field("start", &self.start)
+            .field("offset", &self.offset)
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+impl MemoryHandler for FileBacked {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+    fn map(&self, _pt: &mut PageTableImpl, _va: usize, _attr: &MemoryAttr) {
+        // 映射在第一次访问时才建立，见 handle_page_fault
+    }
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        let (pa, dirty) = match pt.get_entry(va) {
+            Some(entry) if !entry.is_unused() => (entry.target(), entry.dirty()),
+            // 从未被访问过的页没有映射
+            _ => return,
+        };
+        if self.shared && dirty {
+            self.write_back(va, pa);
+        }
+        pt.unmap(va);
+        let frame = Frame::of_addr(PhysAddr::new(pa));
+        if frame_ref_decrease(&frame) == 0 {
+            dealloc_frame(frame);
+        }
+    }
+    fn page_copy(&self, _pt: &mut PageTableImpl, _va: usize, _src: usize, _length: usize) {
+        // 内容总是来自文件，不需要另外复制
+    }
+    fn clone_map(&self, pt: &mut PageTableImpl, src_pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
+        let src_pa = match src_pt.get_entry(va) {
+            Some(entry) if !entry.is_unused() => entry.target(),
+            // 原页表中还未访问过的页，在新页表中同样等到访问时再读入
+            _ => return,
+        };
+        if self.shared {
+            // 共享映射的双方使用同一个物理页帧
+            attr.apply(pt.map(va, src_pa));
+            frame_ref_increase(&Frame::of_addr(PhysAddr::new(src_pa)));
+        } else {
+            // 私有映射复制一份
+            let frame = alloc_frame().expect("alloc_frame failed!");
+            frame_ref_increase(&frame);
+            let pa = frame.start_address().as_usize();
+            unsafe {
+                let dst = core::slice::from_raw_parts_mut(
+                    access_pa_via_va(pa) as *mut u8,
+                    PAGE_SIZE,
+                );
+                let src = core::slice::from_raw_parts(
+                    access_pa_via_va(src_pa) as *const u8,
                     PAGE_SIZE,
                 );
-                for i in 0..length { dst[i] = src[i]; }
+                dst.copy_from_slice(src);
             }
-            for i in length..PAGE_SIZE { dst[i] = 0; }
+            attr.apply(pt.map(va, pa));
         }
     }
-}
\ No newline at end of file
+    fn handle_page_fault(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> bool {
+        // 已经建立了映射的页触发的异常（如写入只读的页）不由我们处理
+        if let Some(entry) = pt.get_entry(va) {
+            if !entry.is_unused() {
+                return false;
+            }
+        }
+        // 分配物理页帧，并从文件中读入这一页的内容，超出文件末尾的部分填 0
+        let frame = alloc_frame().expect("alloc_frame failed!");
+        frame_ref_increase(&frame);
+        let pa = frame.start_address().as_usize();
+        let dst = unsafe {
+            core::slice::from_raw_parts_mut(access_pa_via_va(pa) as *mut u8, PAGE_SIZE)
+        };
+        let length = self.inode.read_at(self.file_offset(va), dst).unwrap_or(0);
+        for i in length..PAGE_SIZE { dst[i] = 0; }
+        attr.apply(pt.map(va & !(PAGE_SIZE - 1), pa));
+        true
+    }
+}
@@ -4,7 +4,7 @@ pub mod area;
 
 use area::MemoryArea;
 use attr::MemoryAttr;
-use crate::memory::paging::PageTableImpl;
+use crate::memory::paging::{ PageTableImpl, PageRange };
 use crate::consts::*;
 use handler::{
     MemoryHandler,
@@ -43,13 +43,118 @@ impl MemorySet {
         // 更新本 MemorySet 的 MemoryArea 集合
         self.areas.push(area);
     }
-    fn test_free_area(&self, start: usize, end: usize) -> bool {
+    pub fn test_free_area(&self, start: usize, end: usize) -> bool {
         // 迭代器的基本应用
         self.areas
             .iter()
             .find(|area| area.is_overlap_with(start, end))
             .is_none()
     }
+    // 删除虚拟地址区间 [start, end) 内的全部映射，start, end 应当页对齐
+    // 完全落在其中的 MemoryArea 被整个删除，部分重叠的 MemoryArea 被拆分，只保留其余的部分
+    pub fn remove(&mut self, start: usize, end: usize) {
+        let page_table = &mut self.page_table;
+        let mut areas = Vec::new();
+        for area in self.areas.drain(..) {
+            // 空区间（如尚未扩大的用户堆）不包含任何虚拟页，原样保留
+            if area.start() == area.end() || !area.is_overlap_with(start, end) {
+                areas.push(area);
+                continue;
+            }
+            area.unmap_range(page_table, start.max(area.start()), end.min(area.end()));
+            if area.start() < start {
+                areas.push(area.sub_area(area.start(), start));
+            }
+            if area.end() > end {
+                areas.push(area.sub_area(end, area.end()));
+            }
+        }
+        self.areas = areas;
+    }
+    // [start, end) 中的每一页是否都在某个用户态可读的 MemoryArea 中，write 表示还要求可写
+    pub fn check_user_range(&self, start: usize, end: usize, write: bool) -> bool {
+        PageRange::new(start, end).all(|page| {
+            self.areas.iter().any(|area| {
+                let attr = area.attr();
+                area.is_overlap_with(page, page + PAGE_SIZE)
+                    && attr.is_user()
+                    && attr.readable()
+                    && (!write || attr.writable())
+            })
+        })
+    }
+    // 虚拟地址 va 所在的虚拟页的权限，该页未被映射时返回 None
+    pub fn attr_at(&self, va: usize) -> Option<&MemoryAttr> {
+        self.areas
+            .iter()
+            .find(|area| area.contains(va))
+            .map(|area| area.attr())
+    }
+    // 将虚拟地址区间 [start, end) 的权限修改为 attr ，start, end 应当页对齐
+    // 部分重叠的 MemoryArea 被拆分，只修改落在其中的部分
+    // 返回值表示是否修改成功：区间中有未被映射的部分时失败，此时不做任何修改
+    pub fn protect(&mut self, start: usize, end: usize, attr: MemoryAttr) -> bool {
+        let covered = PageRange::new(start, end).all(|page| {
+            self.areas.iter().any(|area| area.is_overlap_with(page, page + PAGE_SIZE))
+        });
+        if !covered {
+            return false;
+        }
+        let page_table = &mut self.page_table;
+        let mut areas = Vec::new();
+        for area in self.areas.drain(..) {
+            if area.start() == area.end() || !area.is_overlap_with(start, end) {
+                areas.push(area);
+                continue;
+            }
+            if area.start() < start {
+                areas.push(area.sub_area(area.start(), start));
+            }
+            if area.end() > end {
+                areas.push(area.sub_area(end, area.end()));
+            }
+            let mut middle = area.sub_area(start.max(area.start()), end.min(area.end()));
+            middle.protect(page_table, attr.clone());
+            areas.push(middle);
+        }
+        self.areas = areas;
+        true
+    }
+    // 从 start 开始向高地址寻找一段长度为 len 且未被占据的虚拟地址区间，返回其起始地址
+    // start, len 应当页对齐，找到的区间总是位于用户栈以下的用户地址空间中
+    // 这样的区间不存在时返回 None
+    pub fn find_free_area(&self, start: usize, len: usize) -> Option<usize> {
+        let mut start = start;
+        loop {
+            let end = start.checked_add(len)?;
+            if end > USER_STACK_OFFSET {
+                return None;
+            }
+            // 跳过与之重叠的 MemoryArea
+            match self.areas.iter().find(|area| area.is_overlap_with(start, end)) {
+                Some(area) => start = area.end().checked_add(PAGE_SIZE - 1)? / PAGE_SIZE * PAGE_SIZE,
+                None => return Some(start),
+            }
+        }
+    }
+    // 将起始地址为 start 的 MemoryArea 的结束地址调整为 end ，两者都应当页对齐
+    // 返回值表示是否调整成功：找不到该区间，或者变长的部分与其他区间重叠时失败
+    pub fn resize_area(&mut self, start: usize, end: usize) -> bool {
+        let old_end = match self.areas.iter().find(|area| area.start() == start) {
+            Some(area) => area.end(),
+            None => return false,
+        };
+        if end < start || (end > old_end && !self.test_free_area(old_end, end)) {
+            return false;
+        }
+        let page_table = &mut self.page_table;
+        self.areas
+            .iter_mut()
+            .find(|area| area.start() == start)
+            .unwrap()
+            .resize(page_table, end);
+        true
+    }
     // 将 CPU 所在的虚拟地址空间切换为本 MemorySet
     pub unsafe fn activate(&self) {
         // 这和切换到存储其全部映射的页表是一码事
@@ -123,4 +228,60 @@ impl MemorySet {
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
-}
\ No newline at end of file
+    // 访问虚拟地址 va 时发生缺页异常
+    // 找到 va 所在的 MemoryArea 并交给它处理
+    // 返回值表示异常是否被成功处理
+    pub fn handle_page_fault(&mut self, va: usize) -> bool {
+        let page_table = &mut self.page_table;
+        match self.areas.iter().find(|area| area.contains(va)) {
+            Some(area) => area.handle_page_fault(page_table, va),
+            None => false,
+        }
+    }
+    // 将 data 写入本虚拟内存空间中从 va 开始的位置
+    // 本虚拟内存空间未必正在被使用，因此需要逐页通过页表找到对应的物理页帧再写入
+    // 还没有分配物理页帧或者已被换出的页（见 Lazy）先按照缺页异常进行处理
+    // 返回值表示是否写入成功：遇到未被映射的页时失败，此时之前的页可能已被写入
+    pub fn write_at(&mut self, va: usize, data: &[u8]) -> bool {
+        let mut written = 0;
+        while written < data.len() {
+            let va = va + written;
+            let offset = va % PAGE_SIZE;
+            let length = (PAGE_SIZE - offset).min(data.len() - written);
+            if self.page_table.get_entry(va).map_or(true, |entry| entry.is_unused() || entry.swap_slot().is_some()) {
+                self.handle_page_fault(va);
+            }
+            let pa = match self.page_table.get_entry(va) {
+                Some(entry) if !entry.is_unused() && entry.swap_slot().is_none() => entry.target() + offset,
+                _ => return false,
+            };
+            unsafe {
+                core::slice::from_raw_parts_mut(access_pa_via_va(pa) as *mut u8, length)
+                    .copy_from_slice(&data[written..written + length]);
+            }
+            written += length;
+        }
+        true
+    }
+    // 复制出一个新的虚拟内存空间
+    // 使用一个新的页表，并交给每个 MemoryArea 依次复制其映射及内容
+    pub fn clone(&mut self) -> MemorySet {
+        let mut page_table = PageTableImpl::new_bare();
+        for area in self.areas.iter() {
+            area.clone_map(&mut page_table, &mut self.page_table);
+        }
+        MemorySet {
+            areas: self.areas.clone(),
+            page_table,
+        }
+    }
+}
+impl Drop for MemorySet {
+    // 销毁虚拟内存空间时删除全部映射，由各 MemoryHandler 回收其物理页帧
+    // 页表本身所在的物理页帧则在 PageTableImpl 被销毁时回收
+    fn drop(&mut self) {
+        for area in self.areas.iter() {
+            area.unmap(&mut self.page_table);
+        }
+    }
+}
@@ -1,7 +1,12 @@
 mod frame_allocator;
 pub mod paging;
 pub mod memory_set;
-use frame_allocator::SEGMENT_TREE_ALLOCATOR as FRAME_ALLOCATOR;
+pub mod user;
+pub mod swap;
+use frame_allocator::{
+    SEGMENT_TREE_ALLOCATOR as FRAME_ALLOCATOR,
+    FRAME_REF_COUNT
+};
 use riscv::register::sstatus;
 use riscv::addr::{
     // 分别为虚拟地址、物理地址、虚拟页、物理页帧
@@ -25,17 +30,41 @@ pub fn init(l: usize, r: usize) {
         sstatus::set_sum();
     }
     FRAME_ALLOCATOR.lock().init(l, r);
+    FRAME_REF_COUNT.lock().init(l, r);
     init_heap();
     kernel_remap();
     println!("++++ setup memory!    ++++");
 }
 pub fn alloc_frame() -> Option<Frame> {
     //将物理页号转为物理页帧
-    Some(Frame::of_ppn(FRAME_ALLOCATOR.lock().alloc()))
+    FRAME_ALLOCATOR.lock().alloc().map(Frame::of_ppn)
 }
 pub fn dealloc_frame(f: Frame) {
     FRAME_ALLOCATOR.lock().dealloc(f.number())
 }
+// 分配 count 个物理地址连续的物理页帧，第一个物理页帧的物理页号为 align 的倍数
+// 返回第一个物理页帧
+pub fn alloc_frames(count: usize, align: usize) -> Option<Frame> {
+    FRAME_ALLOCATOR.lock().alloc_contiguous(count, align).map(Frame::of_ppn)
+}
+// 回收从物理页帧 f 开始的 count 个连续的物理页帧
+pub fn dealloc_frames(f: Frame, count: usize) {
+    FRAME_ALLOCATOR.lock().dealloc_contiguous(f.number(), count)
+}
+// 当前剩余的可用物理页帧数
+pub fn free_frame_count() -> usize {
+    FRAME_ALLOCATOR.lock().free_count()
+}
+// 物理页帧的引用计数，只有写时复制的物理页帧才会使用
+pub fn frame_ref_count(f: &Frame) -> usize {
+    FRAME_REF_COUNT.lock().get(f.number())
+}
+pub fn frame_ref_increase(f: &Frame) -> usize {
+    FRAME_REF_COUNT.lock().increase(f.number())
+}
+pub fn frame_ref_decrease(f: &Frame) -> usize {
+    FRAME_REF_COUNT.lock().decrease(f.number())
+}
 
 fn init_heap() {
     // 同样是在内核中开一块静态内存供 buddy system allocator 使用
@@ -83,6 +112,8 @@ pub fn kernel_remap() {
     unsafe {
         memory_set.activate();
     }
+    // 内核的虚拟内存空间将一直被使用，不能在这里被销毁
+    core::mem::forget(memory_set);
 }
 
 #[global_allocator]
@@ -51,6 +51,31 @@ impl PageEntry {
         self.0.flags_mut().set(EF::EXECUTABLE, value);
     }
 
+    // 页表项是否为空，即从未建立过映射
+    pub fn is_unused(&self) -> bool { self.0.is_unused() }
+
+    // 页被换出到交换区时，页表项不合法，用保留给软件使用的 RSW 位进行标记
+    // 并在原本存放物理页号的位置记录页的内容在交换区中的位置
+    pub fn swap_slot(&self) -> Option<usize> {
+        if self.0.flags().contains(EF::RESERVED1) && !self.0.flags().contains(EF::VALID) {
+            Some(self.0.addr().as_usize() / PAGE_SIZE)
+        } else {
+            None
+        }
+    }
+    pub fn set_swap_slot(&mut self, slot: usize) {
+        self.0.set(Frame::of_ppn(slot), EF::RESERVED1);
+    }
+    // 将页表项设置为映射到物理地址 target ，清空所有标志位，之后由 MemoryAttr::apply 设置权限
+    pub fn set_frame(&mut self, target: usize) {
+        self.0.set(Frame::of_addr(PhysAddr::new(target)), EF::empty());
+    }
+
+    // 页表项所对应的虚拟页的起始地址
+    pub fn page_address(&self) -> usize {
+        self.1.start_address().as_usize()
+    }
+
     pub fn target(&self) -> usize {
         self.0.addr().as_usize()
     }
@@ -61,6 +86,20 @@ impl PageEntry {
     }
 }
 
+// 在 token 所描述的页表中找到虚拟地址 va 所在虚拟页的页表项
+// 这个页表未必正在被使用，也未必属于当前线程
+pub fn get_entry_by_token(token: usize, va: usize) -> Option<PageEntry> {
+    // token 的低 44 位为三级页表所在的物理页号
+    let root_paddr = (token & ((1 << 44) - 1)) * PAGE_SIZE;
+    let table = unsafe { &mut *(access_pa_via_va(root_paddr) as *mut PageTableEntryArray) };
+    let mut page_table = Rv39PageTable::new(table, PHYSICAL_MEMORY_OFFSET);
+    let page = Page::of_addr(VirtAddr::new(va));
+    match page_table.ref_entry(page.clone()) {
+        Ok(e) => Some(PageEntry(unsafe { &mut *(e as *mut PageTableEntry) }, page)),
+        Err(_) => None,
+    }
+}
+
 struct FrameAllocatorForPaging;
 
 impl FrameAllocator for FrameAllocatorForPaging {
@@ -129,6 +168,11 @@ impl PageTableImpl {
         // 删除一对映射
         // 我们只需输入虚拟页，因为已经可以找到页表项了
         let page = Page::of_addr(VirtAddr::new(va));
+        // 被设为不存在的页表项（如完全不可访问的页）仍然是一个映射
+        // 先将其恢复为存在，再交给 Rv39PageTable 删除
+        if let Some(entry) = self.get_entry(va) {
+            entry.set_present(true);
+        }
         // 利用 Rv39PageTable 的 unmap 接口
         // * 注意这里没有用到物理页帧管理，所以 Rv39PageTable 并不会回收内存？
         let (_, flush) = self.page_table.unmap(page).unwrap();
@@ -180,6 +224,34 @@ impl PageTableImpl {
     }
 }
 
+impl PageTableImpl {
+    // 回收物理页帧 frame 中的页表以及它的各级子页表，level 为该页表的级数
+    // 叶子页表项所指向的物理页帧不在这里回收
+    fn dealloc_table(frame: Frame, level: usize) {
+        let table = unsafe {
+            &mut *(access_pa_via_va(frame.start_address().as_usize()) as *mut PageTableEntryArray)
+        };
+        if level > 1 {
+            for i in 0..512 {
+                let flags = table[i].flags();
+                // 合法但 R,W,X 均为 0 的页表项指向下一级页表
+                if flags.contains(EF::VALID)
+                    && !flags.intersects(EF::READABLE | EF::WRITABLE | EF::EXECUTABLE) {
+                    Self::dealloc_table(Frame::of_addr(table[i].addr()), level - 1);
+                }
+            }
+        }
+        dealloc_frame(frame);
+    }
+}
+
+impl Drop for PageTableImpl {
+    // 回收三级页表及其下的全部二级、一级页表所在的物理页帧
+    fn drop(&mut self) {
+        Self::dealloc_table(Frame::of_ppn(self.root_frame.number()), 3);
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct PageRange {
@@ -0,0 +1,305 @@
+// 页面置换：物理页帧不足时，将用户页换出到交换区，再次访问时触发缺页异常将其换入
+// 目前只有按需分配物理页帧的页（见 handler::Lazy）会被换出，如用户栈、用户堆以及匿名映射
+use crate::consts::*;
+use crate::fs::device::MemBuf;
+use crate::memory::{
+    alloc_frame,
+    dealloc_frame,
+    free_frame_count,
+    frame_ref_count,
+    frame_ref_increase,
+    frame_ref_decrease,
+    access_pa_via_va,
+};
+use crate::memory::paging::{ PageEntry, get_entry_by_token };
+use crate::memory::memory_set::attr::MemoryAttr;
+use rcore_fs::dev::Device;
+use riscv::addr::{ Frame, PhysAddr };
+use alloc::{ boxed::Box, collections::VecDeque, vec::Vec };
+use spin::Mutex;
+use lazy_static::*;
+
+// 一个可以被换出的用户页：token 所描述的页表中的虚拟页 va
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SwapPage {
+    token: usize,
+    va: usize,
+}
+
+impl SwapPage {
+    // 找到这一页的页表项
+    fn entry(&self) -> Option<PageEntry> {
+        get_entry_by_token(self.token, self.va)
+    }
+}
+
+// 页面置换算法
+pub trait ReplacePolicy: Send {
+    // 一个页被分配了物理页帧，此后可以被换出
+    fn push(&mut self, page: SwapPage);
+    // 一个页的映射被删除，不再需要换出
+    fn remove(&mut self, page: SwapPage);
+    // 选出一个页换出，并将其从算法中删除
+    fn pick(&mut self) -> Option<SwapPage>;
+    // 算法中记录的页数，即驻留在内存中的可换出的页数
+    fn len(&self) -> usize;
+    // 算法中是否没有记录任何页
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// 先进先出：换出最早被分配物理页帧的页
+pub struct FifoPolicy {
+    queue: VecDeque<SwapPage>,
+}
+
+impl FifoPolicy {
+    pub fn new() -> Self {
+        FifoPolicy { queue: VecDeque::new() }
+    }
+}
+
+impl ReplacePolicy for FifoPolicy {
+    fn push(&mut self, page: SwapPage) {
+        self.queue.push_back(page);
+    }
+    fn remove(&mut self, page: SwapPage) {
+        self.queue.retain(|&p| p != page);
+    }
+    fn pick(&mut self) -> Option<SwapPage> {
+        self.queue.pop_front()
+    }
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// 时钟算法：所有页排成一圈，指针依次扫过
+// 遇到访问位为 1 的页将其清零，给它第二次机会；遇到访问位为 0 的页将其换出
+// 队首即为指针所指的位置
+pub struct ClockPolicy {
+    queue: VecDeque<SwapPage>,
+}
+
+impl ClockPolicy {
+    pub fn new() -> Self {
+        ClockPolicy { queue: VecDeque::new() }
+    }
+}
+
+impl ReplacePolicy for ClockPolicy {
+    fn push(&mut self, page: SwapPage) {
+        // 新的页插在指针之前，即最后才会被扫到
+        self.queue.push_back(page);
+    }
+    fn remove(&mut self, page: SwapPage) {
+        self.queue.retain(|&p| p != page);
+    }
+    fn pick(&mut self) -> Option<SwapPage> {
+        loop {
+            let page = self.queue.pop_front()?;
+            match page.entry() {
+                Some(mut entry) if entry.accessed() => {
+                    entry.clear_accessed();
+                    entry.update();
+                    self.queue.push_back(page);
+                },
+                _ => return Some(page),
+            }
+        }
+    }
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// 改进的时钟算法：同时考虑访问位 A 与修改位 D ，优先换出最近没有被访问、也没有被修改过的页
+// 第一圈寻找 (A, D) = (0, 0) 的页，不修改任何标志位
+// 第二圈寻找 (0, 1) 的页，同时将扫过的页的访问位清零
+// 都找不到时重复上述过程，此时所有页的访问位均已为 0 ，一定能找到
+pub struct EnhancedClockPolicy {
+    queue: VecDeque<SwapPage>,
+}
+
+impl EnhancedClockPolicy {
+    pub fn new() -> Self {
+        EnhancedClockPolicy { queue: VecDeque::new() }
+    }
+}
+
+impl ReplacePolicy for EnhancedClockPolicy {
+    fn push(&mut self, page: SwapPage) {
+        self.queue.push_back(page);
+    }
+    fn remove(&mut self, page: SwapPage) {
+        self.queue.retain(|&p| p != page);
+    }
+    fn pick(&mut self) -> Option<SwapPage> {
+        if self.queue.is_empty() {
+            return None;
+        }
+        loop {
+            // 第一圈
+            for _ in 0..self.queue.len() {
+                let page = self.queue[0];
+                match page.entry() {
+                    Some(entry) if entry.accessed() || entry.dirty() => self.queue.rotate_left(1),
+                    // 找不到页表项的页同样交给调用者处理
+                    _ => return self.queue.pop_front(),
+                }
+            }
+            // 第二圈
+            for _ in 0..self.queue.len() {
+                let page = self.queue[0];
+                match page.entry() {
+                    Some(mut entry) if entry.accessed() => {
+                        entry.clear_accessed();
+                        entry.update();
+                        self.queue.rotate_left(1);
+                    },
+                    _ => return self.queue.pop_front(),
+                }
+            }
+        }
+    }
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// 可供选择的页面置换算法
+#[allow(dead_code)]
+enum PolicyKind {
+    Fifo,
+    Clock,
+    EnhancedClock,
+}
+// 使用改进的时钟算法
+const POLICY: PolicyKind = PolicyKind::EnhancedClock;
+
+pub struct SwapManager {
+    // 交换区所在的设备，以页为单位使用
+    device: Box<dyn Device>,
+    // 交换区中空闲的位置
+    free_slots: Vec<usize>,
+    // 页面置换算法
+    policy: Box<dyn ReplacePolicy>,
+}
+
+impl SwapManager {
+    pub fn new(device: Box<dyn Device>, size: usize, policy: Box<dyn ReplacePolicy>) -> Self {
+        SwapManager {
+            device,
+            // 优先使用编号较小的位置
+            free_slots: (0..size / PAGE_SIZE).rev().collect(),
+            policy,
+        }
+    }
+    // 是否需要先换出一页才能分配新的物理页帧
+    fn need_swap_out(&self) -> bool {
+        self.policy.len() >= SWAP_MAX_RESIDENT_PAGES || free_frame_count() < SWAP_MIN_FREE_FRAMES
+    }
+    // 按照页面置换算法选出一页并换出，返回是否成功
+    fn swap_out(&mut self) -> bool {
+        // 被共享的页无法换出，至多尝试算法中记录的页数这么多次
+        for _ in 0..self.policy.len() {
+            let page = match self.policy.pick() {
+                Some(page) => page,
+                None => return false,
+            };
+            let mut entry = match page.entry() {
+                Some(entry) if !entry.is_unused() && entry.swap_slot().is_none() => entry,
+                // 已经不存在的页直接丢弃
+                _ => continue,
+            };
+            let frame = Frame::of_addr(PhysAddr::new(entry.target()));
+            // 写时复制而被多个页表共享的物理页帧暂不换出
+            if frame_ref_count(&frame) > 1 {
+                self.policy.push(page);
+                continue;
+            }
+            let slot = match self.free_slots.pop() {
+                Some(slot) => slot,
+                None => {
+                    self.policy.push(page);
+                    return false;
+                }
+            };
+            let data = unsafe {
+                core::slice::from_raw_parts(
+                    access_pa_via_va(frame.start_address().as_usize()) as *const u8,
+                    PAGE_SIZE,
+                )
+            };
+            self.device.write_at(slot * PAGE_SIZE, data).expect("failed to write swap device!");
+            entry.set_swap_slot(slot);
+            entry.update();
+            frame_ref_decrease(&frame);
+            dealloc_frame(frame);
+            return true;
+        }
+        false
+    }
+    // 在分配物理页帧前调用，必要时换出一页
+    fn reserve(&mut self) {
+        if self.need_swap_out() {
+            self.swap_out();
+        }
+    }
+    // 将被换出的页换入，并按照 attr 设置其权限，之后它可以再次被换出
+    fn swap_in(&mut self, token: usize, entry: &mut PageEntry, attr: &MemoryAttr) {
+        let slot = entry.swap_slot().expect("page is not swapped out!");
+        self.reserve();
+        let frame = alloc_frame().expect("alloc_frame failed!");
+        frame_ref_increase(&frame);
+        let pa = frame.start_address().as_usize();
+        let data = unsafe {
+            core::slice::from_raw_parts_mut(access_pa_via_va(pa) as *mut u8, PAGE_SIZE)
+        };
+        self.device.read_at(slot * PAGE_SIZE, data).expect("failed to read swap device!");
+        self.free_slots.push(slot);
+        entry.set_frame(pa);
+        attr.apply(entry);
+        entry.update();
+        self.policy.push(SwapPage { token, va: entry.page_address() });
+    }
+}
+
+lazy_static! {
+    pub static ref SWAP: Mutex<SwapManager> = {
+        // 使用内核中的一块静态内存模拟交换区所在的磁盘
+        static mut SWAP_SPACE: [u8; SWAP_SIZE] = [0; SWAP_SIZE];
+        let device = unsafe {
+            let start = SWAP_SPACE.as_ptr() as usize;
+            MemBuf::new(start, start + SWAP_SIZE)
+        };
+        let policy: Box<dyn ReplacePolicy> = match POLICY {
+            PolicyKind::Fifo => Box::new(FifoPolicy::new()),
+            PolicyKind::Clock => Box::new(ClockPolicy::new()),
+            PolicyKind::EnhancedClock => Box::new(EnhancedClockPolicy::new()),
+        };
+        Mutex::new(SwapManager::new(Box::new(device), SWAP_SIZE, policy))
+    };
+}
+
+// 即将分配一个物理页帧，必要时先换出一页
+pub fn reserve() {
+    SWAP.lock().reserve();
+}
+// token 所描述的页表中的虚拟页 va 被分配了物理页帧，此后可以被换出
+pub fn track(token: usize, va: usize) {
+    SWAP.lock().policy.push(SwapPage { token, va: va & !(PAGE_SIZE - 1) });
+}
+// token 所描述的页表中的虚拟页 va 的映射即将被删除
+pub fn untrack(token: usize, va: usize) {
+    SWAP.lock().policy.remove(SwapPage { token, va: va & !(PAGE_SIZE - 1) });
+}
+// 将页表项 entry 对应的被换出的页换入
+pub fn swap_in(token: usize, entry: &mut PageEntry, attr: &MemoryAttr) {
+    SWAP.lock().swap_in(token, entry, attr);
+}
+// 被换出的页的映射被删除，释放其在交换区中的位置
+pub fn free_slot(slot: usize) {
+    SWAP.lock().free_slots.push(slot);
+}
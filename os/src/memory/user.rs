@@ -0,0 +1,162 @@
+use crate::process;
+use crate::syscall::{ EFAULT, EINVAL, E2BIG };
+use crate::consts::PAGE_SIZE;
+use alloc::{ string::String, vec::Vec };
+
+// 逐字节复制 len 字节，从 src 复制到 dst ，成功时返回 0
+// 若复制过程中访问用户内存触发了无法处理的缺页异常
+// 中断处理会将 sepc 修改为 __copy_user_fixup ，于是返回 1
+global_asm!(r"
+    .section .text
+    .globl __copy_user
+__copy_user:
+    beqz a2, 2f
+1:
+    lb t0, 0(a1)
+    sb t0, 0(a0)
+    addi a0, a0, 1
+    addi a1, a1, 1
+    addi a2, a2, -1
+    bnez a2, 1b
+2:
+    li a0, 0
+    ret
+    .globl __copy_user_fixup
+__copy_user_fixup:
+    li a0, 1
+    ret
+");
+
+extern "C" {
+    fn __copy_user(dst: usize, src: usize, len: usize) -> usize;
+    fn __copy_user_fixup();
+}
+
+// 若在 __copy_user 中访问用户内存时发生了无法处理的缺页异常
+// 返回应当跳转到的错误处理代码的地址
+pub fn fixup(sepc: usize) -> Option<usize> {
+    let start = __copy_user as usize;
+    let end = __copy_user_fixup as usize;
+    if sepc >= start && sepc < end {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+// 当前进程中 [start, start + len) 是否均为用户态可访问的虚拟地址，write 表示还要求可写
+// 注意调用时不能持有当前进程的锁
+fn check_user_range(start: usize, len: usize, write: bool) -> bool {
+    if len == 0 {
+        return true;
+    }
+    let end = match start.checked_add(len) {
+        Some(end) => end,
+        None => return false,
+    };
+    match process::current_process() {
+        Some(proc) => proc.lock().vm.check_user_range(start, end, write),
+        None => false,
+    }
+}
+
+// 将用户态虚拟地址 src 开始的 dst.len() 字节复制到 dst 中
+// 地址不合法时返回 -EFAULT
+pub fn copy_from_user(dst: &mut [u8], src: usize) -> Result<(), isize> {
+    if !check_user_range(src, dst.len(), false) {
+        return Err(-EFAULT);
+    }
+    match unsafe { __copy_user(dst.as_mut_ptr() as usize, src, dst.len()) } {
+        0 => Ok(()),
+        _ => Err(-EFAULT),
+    }
+}
+
+// 将 src 复制到用户态虚拟地址 dst 开始的位置
+// 地址不合法时返回 -EFAULT
+pub fn copy_to_user(dst: usize, src: &[u8]) -> Result<(), isize> {
+    if !check_user_range(dst, src.len(), true) {
+        return Err(-EFAULT);
+    }
+    match unsafe { __copy_user(dst, src.as_ptr() as usize, src.len()) } {
+        0 => Ok(()),
+        _ => Err(-EFAULT),
+    }
+}
+
+// 从用户态虚拟地址 src 处读入一个类型为 T 的值
+pub fn read_user<T: Copy>(src: usize) -> Result<T, isize> {
+    let mut value: T = unsafe { core::mem::zeroed() };
+    let dst = unsafe {
+        core::slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, core::mem::size_of::<T>())
+    };
+    copy_from_user(dst, src)?;
+    Ok(value)
+}
+
+// 将类型为 T 的值 value 写入用户态虚拟地址 dst 处
+pub fn write_user<T: Copy>(dst: usize, value: &T) -> Result<(), isize> {
+    let src = unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    copy_to_user(dst, src)
+}
+
+// 从用户态虚拟地址 src 处读入一个以 \0 结尾的字符串
+// 地址不合法时返回 -EFAULT ，不是合法的 UTF-8 编码时返回 -EINVAL
+pub fn read_user_cstr(src: usize) -> Result<String, isize> {
+    read_user_cstr_max(src, core::usize::MAX)
+}
+
+// 同 read_user_cstr ，但字符串长度超过 max 字节时返回 -E2BIG
+fn read_user_cstr_max(src: usize, max: usize) -> Result<String, isize> {
+    let mut bytes = Vec::new();
+    let mut va = src;
+    loop {
+        if bytes.len() > max {
+            return Err(-E2BIG);
+        }
+        // 每次读入到当前页的末尾为止
+        let mut buf = [0u8; PAGE_SIZE];
+        let len = PAGE_SIZE - va % PAGE_SIZE;
+        copy_from_user(&mut buf[..len], va)?;
+        match buf[..len].iter().position(|&ch| ch == 0) {
+            Some(pos) => {
+                bytes.extend_from_slice(&buf[..pos]);
+                break;
+            },
+            None => bytes.extend_from_slice(&buf[..len]),
+        }
+        va += len;
+    }
+    if bytes.len() > max {
+        return Err(-E2BIG);
+    }
+    String::from_utf8(bytes).map_err(|_| -EINVAL)
+}
+
+// 从用户态虚拟地址 src 处读入一个以空指针结尾的字符串指针数组
+// 数组本身为空指针时视为空数组
+// 每个字符串占用其长度加上 \0 与一个指针的大小，从 limit 中扣除，不够扣除时返回 -E2BIG
+pub fn read_user_cstr_array(src: usize, limit: &mut usize) -> Result<Vec<String>, isize> {
+    let mut strings = Vec::new();
+    if src == 0 {
+        return Ok(strings);
+    }
+    let mut va = src;
+    loop {
+        let ptr: usize = read_user(va)?;
+        if ptr == 0 {
+            break;
+        }
+        let size = 1 + core::mem::size_of::<usize>();
+        if *limit < size {
+            return Err(-E2BIG);
+        }
+        let s = read_user_cstr_max(ptr, *limit - size)?;
+        *limit -= s.len() + size;
+        strings.push(s);
+        va += core::mem::size_of::<usize>();
+    }
+    Ok(strings)
+}
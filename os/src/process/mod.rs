@@ -3,11 +3,18 @@ pub mod scheduler;
 pub mod thread_pool;
 pub mod processor;
 
-use structs::Thread;
+use structs::{ Thread, Process };
 use processor::Processor;
-use scheduler::RRScheduler;
+use scheduler::{
+    Scheduler,
+    RRScheduler,
+    StrideScheduler,
+    MLFQScheduler
+};
 use thread_pool::ThreadPool;
-use alloc::boxed::Box;
+use alloc::{ boxed::Box, string::String, sync::Arc, vec, vec::Vec };
+use spin::Mutex;
+use crate::context::TrapFrame;
 static CPU: Processor = Processor::new();
 
 use crate::fs::{
@@ -15,9 +22,40 @@ use crate::fs::{
     INodeExt
 };
 
+// 可供选择的调度算法
+#[allow(dead_code)]
+enum SchedulerKind {
+    // 时间片轮转
+    RoundRobin,
+    // 按照 set_priority 设置的优先级按比例分配 CPU 时间
+    Stride,
+    // 多级反馈队列：交互式线程留在高优先级队列，计算密集的线程逐渐降级
+    MLFQ,
+}
+// 使用多级反馈队列调度算法
+const SCHEDULER: SchedulerKind = SchedulerKind::MLFQ;
+
 pub type Tid = usize;
+pub type Pid = usize;
 pub type ExitCode = usize;
 
+// 等待队列（如定时器、条件变量）中记录的一个线程
+// Tid 会被重复使用，因此还要记录线程加入线程池时分配的、互不相同的编号 generation
+// 线程退出后残留在等待队列中的记录因此不会唤醒之后使用同一 Tid 的线程
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Waiter {
+    pub tid: Tid,
+    pub generation: usize,
+}
+
+// execute 失败的原因
+pub enum ExecError {
+    // 找不到路径对应的文件
+    NotFound,
+    // 不是合法的可执行文件
+    NotExecutable,
+}
+
 #[no_mangle]
 pub extern "C" fn hello_thread(arg: usize) -> ! {
     println!("begin of thread {}", arg);
@@ -31,10 +69,15 @@ pub extern "C" fn hello_thread(arg: usize) -> ! {
 }
 
 pub fn init() {
-    // 使用 Round Robin Scheduler
-    let scheduler = RRScheduler::new(1);
+    // 根据 SCHEDULER 选择调度算法
+    let scheduler: Box<dyn Scheduler> = match SCHEDULER {
+        SchedulerKind::RoundRobin => Box::new(RRScheduler::new(1)),
+        SchedulerKind::Stride => Box::new(StrideScheduler::new(1)),
+        // 3 级队列，时间片依次为 1, 2, 4 个 tick ，每 100 个 tick 进行一次优先级提升
+        SchedulerKind::MLFQ => Box::new(MLFQScheduler::new(3, 1, 100)),
+    };
     // 新建线程池
-    let thread_pool = ThreadPool::new(100, Box::new(scheduler));
+    let thread_pool = ThreadPool::new(100, scheduler);
     // 新建内核线程 idle ，其入口为 Processor::idle_main
     let idle = Thread::new_kernel(Processor::idle_main as usize);
     // 我们需要传入 CPU 的地址作为参数
@@ -55,7 +98,9 @@ pub fn init() {
     println!("Initialized kernel thread!");
     */
 
-    execute("rust/user_shell", None);
+    if execute("rust/user_shell", vec![String::from("user_shell")], Vec::new()).is_err() {
+        panic!("failed to start user shell!");
+    }
     println!("++++ setup process!   ++++");
 }
 
@@ -68,9 +113,16 @@ pub fn run() {
 }
 
 pub fn exit(code: usize) {
+    // 当前线程不再回到用户态，先删除其单独使用的用户栈
+    CPU.current_thread().release_ustack();
     CPU.exit(code);
 }
 
+// 结束当前进程中的全部线程
+pub fn exit_group(code: usize) {
+    CPU.exit_group(code);
+}
+
 // 当前线程自动放弃 CPU 资源并进入阻塞状态
 // 线程状态： Running(Tid) -> Sleeping
 pub fn yield_now() {
@@ -78,29 +130,75 @@ pub fn yield_now() {
 }
 // 某些条件满足，线程等待 CPU 资源从而继续执行
 // 线程状态： Sleeping -> Ready
-pub fn wake_up(tid: Tid) {
-    CPU.wake_up(tid);
+// 返回值表示是否唤醒了线程：线程已经退出或被 exit_group 结束时什么也不做
+pub fn wake_up(waiter: Waiter) -> bool {
+    CPU.wake_up(waiter)
+}
+// 当前线程在等待队列中的记录，之后通过 wake_up 将其唤醒
+pub fn current_waiter() -> Waiter {
+    CPU.current_waiter()
+}
+// 等待子线程退出并回收其退出码
+// tid 为 None 表示任意一个子线程，nohang 表示不阻塞
+pub fn wait(tid: Option<Tid>, nohang: bool) -> Result<Option<(Tid, ExitCode)>, ()> {
+    CPU.wait(tid, nohang)
+}
+// 设置当前线程的优先级，交给调度算法使用
+pub fn set_priority(priority: usize) {
+    CPU.set_priority(CPU.current_tid(), priority);
+}
+// 设置是否跟踪当前线程，以及它此后创建的线程的系统调用
+pub fn set_trace(trace: bool, trace_children: bool) {
+    CPU.set_trace(trace, trace_children);
+}
+// 当前线程的系统调用是否被跟踪
+pub fn tracing() -> bool {
+    CPU.tracing()
 }
 // 获取当前线程的 Tid
 pub fn current_tid() -> usize {
     CPU.current_tid()
 }
 
-// 返回值表示是否正常执行
-pub fn execute(path: &str, host_tid: Option<Tid>) -> bool {
-    let find_result = ROOT_INODE.lookup(path);
-    match find_result {
-        Ok(inode) => {
-            let data = inode.read_as_vec().unwrap();
-            // 这里创建用户线程时，传入 host_tid
-            let user_thread = unsafe { Thread::new_user(data.as_slice(), host_tid) };
-            CPU.add_thread(user_thread);
-            true
-        },
-        Err(_) => {
-            // 如果找不到路径字符串对应的用户程序
-            println!("command not found!");
-            false
-        }
-    }
+// 缺页异常处理，返回值表示是否处理成功
+pub fn handle_page_fault(va: usize) -> bool {
+    CPU.handle_page_fault(va)
+}
+
+// 获取当前线程所属进程的进程号，内核线程返回 None
+pub fn current_pid() -> Option<Pid> {
+    CPU.current_thread()
+        .proc
+        .as_ref()
+        .map(|proc| proc.lock().pid.unwrap())
+}
+
+// 获取当前线程所属的进程，内核线程返回 None
+pub fn current_process() -> Option<Arc<Mutex<Process>>> {
+    CPU.current_thread().proc.clone()
+}
+
+// 复制当前线程，返回新线程的 Tid
+pub fn fork(tf: &TrapFrame) -> Tid {
+    let thread = CPU.current_thread().fork(tf);
+    CPU.add_thread(thread)
+}
+
+// 在当前进程中新建一个线程，返回新线程的 Tid
+// 新线程从 entry 开始执行，并传入初始参数 args
+pub fn thread_create(entry: usize, args: [usize; 3]) -> Tid {
+    let thread = CPU.current_thread().new_thread(entry, args);
+    CPU.add_thread(thread)
+}
+
+// 返回新线程的 Tid ，或者未能正常执行的原因
+// 当前线程将成为新线程的父线程
+// args, envs 分别为传给用户程序的参数与环境变量
+pub fn execute(path: &str, args: Vec<String>, envs: Vec<String>) -> Result<Tid, ExecError> {
+    // 如果找不到路径字符串对应的用户程序
+    let inode = ROOT_INODE.lookup(path).map_err(|_| ExecError::NotFound)?;
+    let data = inode.read_as_vec().map_err(|_| ExecError::NotFound)?;
+    let user_thread = unsafe { Thread::new_user(data.as_slice(), args, envs) }
+        .ok_or(ExecError::NotExecutable)?;
+    Ok(CPU.add_thread(user_thread))
 }
\ No newline at end of file
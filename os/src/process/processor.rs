@@ -1,6 +1,6 @@
 use core::cell::UnsafeCell;
 use alloc::boxed::Box;
-use crate::process::Tid;
+use crate::process::{ Tid, ExitCode, Waiter };
 use crate::process::structs::*;
 use crate::process::thread_pool::ThreadPool;
 use crate::interrupt::*;
@@ -44,8 +44,17 @@ impl Processor {
             .expect("Processor is not initialized!")
     }
     // 通过线程池新增线程
-    pub fn add_thread(&self, thread: Box<Thread>) {
-        self.inner().pool.add(thread);
+    // 如果当前有在运行线程，它就是新线程的父线程
+    pub fn add_thread(&self, thread: Box<Thread>) -> Tid {
+        let inner = self.inner();
+        let parent = inner.current.as_ref().map(|(tid, _)| *tid);
+        let proc = thread.proc.clone();
+        let tid = inner.pool.add(thread, parent);
+        // 如果是进程中的第一个线程，进程号就是它的 Tid
+        if let Some(proc) = proc {
+            proc.lock().pid.get_or_insert(tid);
+        }
+        tid
     }
 
     pub fn idle_main(&self) -> ! {
@@ -120,16 +129,10 @@ impl Processor {
         let tid = inner.current.as_ref().unwrap().0;
 
         // 通知线程池这个线程退出啦！
-        inner.pool.exit(tid);
+        // 如果父线程正在等待，线程池会将其唤醒
+        inner.pool.exit(tid, code);
         println!("thread {} exited, exit code = {}", tid, code);
 
-        // 加入这个判断
-        // 如果有一个线程正在等待当前线程运行结束
-        // 将其唤醒
-        if let Some(wait) = inner.current.as_ref().unwrap().1.wait {
-            inner.pool.wakeup(wait);
-        }
-
         // 切换到 idle 线程决定下一个运行哪个线程
         inner.current
             .as_mut()
@@ -140,6 +143,18 @@ impl Processor {
         loop {}
     }
 
+    // 结束当前线程所在进程中的全部线程，退出码均为 code
+    pub fn exit_group(&self, code: usize) -> ! {
+        disable_and_store();
+        let inner = self.inner();
+        let (tid, thread) = inner.current.as_ref().unwrap();
+        if let Some(proc) = thread.proc.as_ref() {
+            inner.pool.kill_others(*tid, proc, code);
+        }
+        // 最后结束当前线程
+        self.exit(code)
+    }
+
     pub fn yield_now(&self) {
         let inner = self.inner();
         if !inner.current.is_none() {
@@ -165,12 +180,69 @@ impl Processor {
         }
     }
 
-    pub fn wake_up(&self, tid: Tid) {
+    // 等待子线程 tid 退出，tid 为 None 表示任意一个子线程
+    // nohang 为 true 时不会阻塞，子线程都还没有退出则直接返回 Ok(None)
+    pub fn wait(&self, tid: Option<Tid>, nohang: bool) -> Result<Option<(Tid, ExitCode)>, ()> {
+        let inner = self.inner();
+        let current_tid = self.current_tid();
+        loop {
+            match inner.pool.wait(current_tid, tid) {
+                Ok(None) if !nohang => {
+                    // 进入睡眠状态，直到有子线程退出时被唤醒
+                    inner.pool.threads[current_tid].as_mut().unwrap().waiting = true;
+                    self.yield_now();
+                },
+                result => return result,
+            }
+        }
+    }
+
+    pub fn set_priority(&self, tid: Tid, priority: usize) {
+        self.inner().pool.set_priority(tid, priority);
+    }
+
+    pub fn wake_up(&self, waiter: Waiter) -> bool {
         let inner = self.inner();
-        inner.pool.wakeup(tid);
+        inner.pool.wakeup(waiter)
+    }
+
+    pub fn current_waiter(&self) -> Waiter {
+        let tid = self.current_tid();
+        let info = self.inner().pool.threads[tid].as_ref().expect("thread not exist!");
+        Waiter { tid, generation: info.generation }
+    }
+
+    // 设置是否跟踪当前线程，以及它此后创建的线程的系统调用
+    pub fn set_trace(&self, trace: bool, trace_children: bool) {
+        let tid = self.current_tid();
+        let info = self.inner().pool.threads[tid].as_mut().expect("thread not exist!");
+        info.trace = trace;
+        info.trace_children = trace_children;
+    }
+
+    // 当前线程的系统调用是否被跟踪
+    pub fn tracing(&self) -> bool {
+        let tid = self.current_tid();
+        self.inner().pool.threads[tid].as_ref().map_or(false, |info| info.trace)
     }
 
     pub fn current_tid(&self) -> usize {
         self.inner().current.as_mut().unwrap().0 as usize
     }
+
+    // 在当前线程的虚拟内存空间中处理缺页异常
+    // 当前没有在运行线程，或是内核线程时无法处理
+    pub fn handle_page_fault(&self, va: usize) -> bool {
+        match self.inner().current.as_ref() {
+            Some((_, thread)) => match thread.proc.as_ref() {
+                Some(proc) => proc.lock().vm.handle_page_fault(va),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    pub fn current_thread(&self) -> &Thread {
+        &self.inner().current.as_ref().unwrap().1
+    }
 }
\ No newline at end of file
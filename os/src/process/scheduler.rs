@@ -1,5 +1,5 @@
 use super::Tid;
-use alloc::vec::Vec;
+use alloc::{ vec::Vec, collections::VecDeque };
 
 pub trait Scheduler {
     // 如果 tid 不存在，表明将一个新线程加入线程调度
@@ -12,6 +12,8 @@ pub trait Scheduler {
     fn tick(&mut self) -> bool;
     // 告诉调度算法一个线程已经结束
     fn exit(&mut self, tid: Tid);
+    // 设置线程的优先级，不支持优先级的调度算法可以忽略
+    fn set_priority(&mut self, _tid: Tid, _priority: usize) {}
 }
 
 #[derive(Default)]
@@ -105,4 +107,222 @@ impl Scheduler for RRScheduler {
             self.current = 0;
         }
     }
-}
\ No newline at end of file
+}
+// Stride 调度算法中的一大步长
+// 线程每被调度一次，其 pass 就增加 BIG_STRIDE / priority
+const BIG_STRIDE: usize = 1 << 20;
+
+#[derive(Default)]
+struct StrideInfo {
+    // 是否在等待被调度
+    valid: bool,
+    // 是否已经加入过调度
+    initialized: bool,
+    time: usize,
+    // 已经走过的总步长
+    pass: usize,
+    // 优先级，越大则分配到的 CPU 时间越多
+    priority: usize,
+}
+
+pub struct StrideScheduler {
+    threads: Vec<StrideInfo>,
+    max_time: usize,
+    current: Option<Tid>,
+}
+
+impl StrideScheduler {
+    // 设置每个线程连续运行的最大 tick 数
+    pub fn new(max_time_slice: usize) -> Self {
+        StrideScheduler {
+            threads: Vec::default(),
+            max_time: max_time_slice,
+            current: None,
+        }
+    }
+    fn get_info(&mut self, tid: Tid) -> &mut StrideInfo {
+        if tid + 1 > self.threads.len() {
+            self.threads.resize_with(tid + 1, Default::default);
+        }
+        &mut self.threads[tid]
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    // 分为 1. 新线程 2. 时间片耗尽被切换出的线程 两种情况
+    fn push(&mut self, tid: Tid) {
+        // 新线程从当前线程的 pass 开始，避免它长时间独占 CPU
+        let current_pass = match self.current {
+            Some(current) => self.threads[current].pass,
+            None => 0,
+        };
+        let max_time = self.max_time;
+        let info = self.get_info(tid);
+        if !info.initialized {
+            info.initialized = true;
+            info.pass = current_pass;
+            if info.priority == 0 {
+                info.priority = 1;
+            }
+        }
+        if info.time == 0 {
+            info.time = max_time;
+        }
+        info.valid = true;
+    }
+
+    // 选择 pass 最小的线程运行，并将它的 pass 增加一个步长
+    fn pop(&mut self) -> Option<Tid> {
+        let next = self.threads
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| info.valid)
+            .min_by_key(|(_, info)| info.pass)
+            .map(|(tid, _)| tid);
+        if let Some(tid) = next {
+            let info = &mut self.threads[tid];
+            info.valid = false;
+            info.pass += BIG_STRIDE / info.priority;
+            self.current = Some(tid);
+        }
+        next
+    }
+
+    // 当前线程的可用时间片 -= 1
+    fn tick(&mut self) -> bool {
+        if let Some(tid) = self.current {
+            let info = &mut self.threads[tid];
+            info.time -= 1;
+            return info.time == 0;
+        }
+        true
+    }
+
+    // 清空线程的信息，之后这个 Tid 可能分配给新的线程
+    fn exit(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        if tid < self.threads.len() {
+            self.threads[tid] = StrideInfo::default();
+        }
+    }
+
+    fn set_priority(&mut self, tid: Tid, priority: usize) {
+        // 优先级至少为 1
+        self.get_info(tid).priority = priority.max(1);
+    }
+}
+
+#[derive(Default)]
+struct MLFQInfo {
+    // 是否已经加入过调度
+    initialized: bool,
+    // 所在队列的级数，0 级优先级最高
+    level: usize,
+    // 在当前级还剩余的时间片
+    time: usize,
+}
+
+pub struct MLFQScheduler {
+    // 各级就绪队列，0 级优先级最高
+    queues: Vec<VecDeque<Tid>>,
+    // 各级队列的时间片长度
+    time_slices: Vec<usize>,
+    threads: Vec<MLFQInfo>,
+    current: Option<Tid>,
+    // 每隔多少个 tick 将所有线程提升到最高级
+    boost_interval: usize,
+    // 距离下一次优先级提升还剩多少个 tick
+    boost_left: usize,
+}
+
+impl MLFQScheduler {
+    // 共有 levels 级队列，第 i 级的时间片为 base_time_slice << i 个 tick
+    // 每隔 boost_interval 个 tick 进行一次优先级提升，防止低优先级线程饥饿
+    pub fn new(levels: usize, base_time_slice: usize, boost_interval: usize) -> Self {
+        MLFQScheduler {
+            queues: (0..levels).map(|_| VecDeque::new()).collect(),
+            time_slices: (0..levels).map(|i| base_time_slice << i).collect(),
+            threads: Vec::default(),
+            current: None,
+            boost_interval,
+            boost_left: boost_interval,
+        }
+    }
+    // 将所有线程提升到最高级，并重新分配时间片
+    fn boost(&mut self) {
+        for level in 1..self.queues.len() {
+            while let Some(tid) = self.queues[level].pop_front() {
+                self.queues[0].push_back(tid);
+            }
+        }
+        let time_slice = self.time_slices[0];
+        for info in self.threads.iter_mut().filter(|info| info.initialized) {
+            info.level = 0;
+            info.time = time_slice;
+        }
+    }
+}
+
+impl Scheduler for MLFQScheduler {
+    // 分为 1. 新线程 2. 时间片耗尽被切换出的线程 3. 主动放弃 CPU 后被唤醒的线程 三种情况
+    fn push(&mut self, tid: Tid) {
+        if tid + 1 > self.threads.len() {
+            self.threads.resize_with(tid + 1, Default::default);
+        }
+        let lowest = self.queues.len() - 1;
+        let info = &mut self.threads[tid];
+        if !info.initialized {
+            // 新线程进入最高级
+            info.initialized = true;
+            info.level = 0;
+            info.time = self.time_slices[0];
+        } else if info.time == 0 {
+            // 用完了整个时间片，降一级
+            info.level = (info.level + 1).min(lowest);
+            info.time = self.time_slices[info.level];
+        }
+        // 否则时间片还没用完就放弃了 CPU ，留在原来的级别
+        self.queues[info.level].push_back(tid);
+    }
+
+    // 从最高级的非空队列中取出队首线程
+    fn pop(&mut self) -> Option<Tid> {
+        let tid = self.queues
+            .iter_mut()
+            .find(|queue| !queue.is_empty())
+            .and_then(|queue| queue.pop_front());
+        if tid.is_some() {
+            self.current = tid;
+        }
+        tid
+    }
+
+    // 当前线程的可用时间片 -= 1 ，同时检查是否需要进行优先级提升
+    fn tick(&mut self) -> bool {
+        self.boost_left -= 1;
+        if self.boost_left == 0 {
+            self.boost_left = self.boost_interval;
+            self.boost();
+        }
+        if let Some(tid) = self.current {
+            let info = &mut self.threads[tid];
+            if info.time > 0 {
+                info.time -= 1;
+            }
+            return info.time == 0;
+        }
+        true
+    }
+
+    // 清空线程的信息，之后这个 Tid 可能分配给新的线程
+    fn exit(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        if tid < self.threads.len() {
+            self.threads[tid] = MLFQInfo::default();
+        }
+    }
+}
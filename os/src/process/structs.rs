@@ -1,4 +1,4 @@
-use crate::context::Context;
+use crate::context::{ Context, TrapFrame };
 use crate::alloc::alloc::{
     alloc,
     dealloc,
@@ -6,18 +6,21 @@ use crate::alloc::alloc::{
 };
 use crate::consts::*;
 use riscv::register::satp;
-use alloc::boxed::Box;
-use super::{ Tid, ExitCode };
+use alloc::{ boxed::Box, sync::Arc, string::String, vec, vec::Vec, collections::BTreeMap };
+use spin::Mutex;
+use super::{ Tid, Pid, ExitCode };
 use xmas_elf::{
     header,
-    program::{ Flags, SegmentData, Type },
+    program::{ Flags, Type },
     ElfFile,
 };
 use crate::memory::memory_set::{
     MemorySet,
-    handler::ByFrame,
+    handler::{ CopyOnWrite, Lazy },
     attr::MemoryAttr,
 };
+use crate::fs::{ ROOT_INODE, INodeExt, file::File };
+use crate::timer::get_cycle;
 use core::str;
 
 pub struct KernelStack(usize);
@@ -55,7 +58,103 @@ pub struct Thread {
     pub context: Context,
     // 线程的栈
     pub kstack: KernelStack,
-    pub wait: Option<Tid>,
+    // 用户线程所属的进程，内核线程为 None
+    pub proc: Option<Arc<Mutex<Process>>>,
+    // 通过 thread_create 新建的线程单独使用的用户栈的栈底，线程退出时将其删除
+    // 主线程的用户栈随进程一起释放，为 None
+    pub ustack: Option<usize>,
+}
+
+// 进程：同一进程中的各线程共享其虚拟内存空间与打开的文件
+pub struct Process {
+    // 进程号，即进程中第一个线程的 Tid ，在其加入线程池时确定
+    pub pid: Option<Pid>,
+    // 虚拟内存空间
+    pub vm: MemorySet,
+    // 文件描述符表，下标即为文件描述符
+    pub files: Vec<Option<File>>,
+    // 用户堆的起始地址，堆所在的 MemoryArea 从这里开始
+    pub heap_start: usize,
+    // 用户堆的当前结束地址，即 program break
+    pub brk: usize,
+}
+
+impl Process {
+    pub fn new(vm: MemorySet) -> Self {
+        Process {
+            pid: None,
+            vm,
+            // 默认打开标准输入 0 、标准输出 1 、标准错误输出 2
+            files: vec![
+                Some(File::Stdin { nonblock: false }),
+                Some(File::Stdout),
+                Some(File::Stdout),
+            ],
+            heap_start: 0,
+            brk: 0,
+        }
+    }
+    // 将打开的文件加入文件描述符表，返回分配到的文件描述符，即最小的空闲下标
+    pub fn add_file(&mut self, file: File) -> usize {
+        match self.files.iter().position(|file| file.is_none()) {
+            Some(fd) => {
+                self.files[fd] = Some(file);
+                fd
+            },
+            None => {
+                self.files.push(Some(file));
+                self.files.len() - 1
+            }
+        }
+    }
+    // 在虚拟内存空间中从 heap_start 开始插入一个空的用户堆，之后通过 set_brk 调整其大小
+    pub fn init_heap(&mut self, heap_start: usize) {
+        self.vm.push(
+            heap_start,
+            heap_start,
+            MemoryAttr::new().set_user(),
+            // 堆中的页等到第一次访问时才分配物理页帧
+            Lazy::new(),
+            None,
+        );
+        self.heap_start = heap_start;
+        self.brk = heap_start;
+    }
+    // 将用户堆的结束地址调整为 brk ，返回调整后的结束地址
+    // 若 brk 不合法或者空间不足，则保持不变
+    pub fn set_brk(&mut self, brk: usize) -> usize {
+        if brk < self.heap_start {
+            return self.brk;
+        }
+        // 堆所在的 MemoryArea 以页为单位伸缩，向上对齐时溢出同样视为不合法
+        let end = match brk.checked_add(PAGE_SIZE - 1) {
+            Some(end) => end / PAGE_SIZE * PAGE_SIZE,
+            None => return self.brk,
+        };
+        if self.vm.resize_area(self.heap_start, end) {
+            self.brk = brk;
+        }
+        self.brk
+    }
+    // 为进程中的一个新线程分配用户栈，返回栈顶地址
+    // 各线程的用户栈从 USER_STACK_OFFSET 开始向上依次排列，相邻两个之间空出一页
+    pub fn alloc_ustack(&mut self) -> usize {
+        let mut ustack_bottom = USER_STACK_OFFSET;
+        while !self.vm.test_free_area(ustack_bottom, ustack_bottom + USER_STACK_SIZE) {
+            ustack_bottom += USER_STACK_SIZE + PAGE_SIZE;
+        }
+        // 将用户栈插入虚拟内存空间
+        self.vm.push(
+            ustack_bottom,
+            ustack_bottom + USER_STACK_SIZE,
+            // 注意这里设置为用户态
+            MemoryAttr::new().set_user(),
+            // 大部分程序只用到栈顶附近的几页，等到访问时再分配物理页帧
+            Lazy::new(),
+            None,
+        );
+        ustack_bottom + USER_STACK_SIZE
+    }
 }
 
 impl Thread {
@@ -66,7 +165,8 @@ impl Thread {
         Box::new(Thread {
             context: Context::null(),
             kstack: KernelStack::new_empty(),
-            wait: None,
+            proc: None,
+            ustack: None,
         })
     }
     pub fn new_kernel(entry: usize) -> Box<Thread> {
@@ -76,57 +176,173 @@ impl Thread {
                 // 内核线程共享内核资源，因此用目前的 satp 即可
                 context: Context::new_kernel_thread(entry, kstack_.top(), satp::read().bits()),
                 kstack: kstack_,
-                wait: None,
+                proc: None,
+                ustack: None,
             })
         }
     }
-    pub unsafe fn new_user(data: &[u8], wait_thread: Option<Tid>) -> Box<Thread> {
+    // args, envs 分别为传给用户程序的参数与环境变量
+    // data 不是合法的可执行文件时返回 None
+    pub unsafe fn new_user(data: &[u8], args: Vec<String>, envs: Vec<String>) -> Option<Box<Thread>> {
         // 确认合法性
-        let elf = ElfFile::new(data).expect("failed to analyse elf!");
+        let elf = match ElfFile::new(data) {
+            Ok(elf) => elf,
+            Err(err) => {
+                println!("failed to analyse elf: {}", err);
+                return None;
+            }
+        };
 
-        match elf.header.pt2.type_().as_type() {
+        // 位置无关的可执行文件 (PIE) 可以被加载到任意位置，我们将其加载到 USER_PIE_BASE
+        // 其余可执行文件的基址为 0 ，即按照其中写好的虚拟地址加载
+        let base = match elf.header.pt2.type_().as_type() {
             header::Type::Executable => {
                 println!("it really a executable!");
+                0
             },
             header::Type::SharedObject => {
-                panic!("shared object is not supported!");
+                println!("it is a position independent executable!");
+                USER_PIE_BASE
             },
             _ => {
-                panic!("unsupported elf type!");
+                println!("unsupported elf type!");
+                return None;
             }
-        }
+        };
         // 获取入口点
-        let entry_addr = elf.header.pt2.entry_point() as usize;
+        let entry_addr = base.wrapping_add(elf.header.pt2.entry_point() as usize);
         // 为用户程序创建新的虚拟内存空间
-        let mut vm = elf.make_memory_set();
-
-        // 创建用户栈
-        let mut ustack_top = {
-            // 这里我们将用户栈固定在虚拟内存空间中的某位置
-            let (ustack_bottom, ustack_top) = (USER_STACK_OFFSET, USER_STACK_OFFSET + USER_STACK_SIZE);
-            // 将用户栈插入虚拟内存空间
-            vm.push(
-                ustack_bottom,
-                ustack_top,
-                // 注意这里设置为用户态
-                MemoryAttr::new().set_user(),
-                ByFrame::new(),
-                None,
-            );
-            ustack_top
+        let mut memory_set = match elf.make_memory_set(base) {
+            Ok(memory_set) => memory_set,
+            Err(err) => {
+                println!("failed to load elf: {}", err);
+                return None;
+            }
+        };
+        // 线程开始执行的位置，以及解释器的基址
+        let (start_addr, interp_base) = match elf.interpreter() {
+            // 动态链接的程序需要先运行解释器（动态链接器），由它加载依赖的共享库并完成重定位
+            // 之后解释器根据 auxv 中的 AT_ENTRY 跳转到程序本身的入口点
+            Some(path) => match load_interpreter(&mut memory_set, path) {
+                Ok((interp_base, interp_entry)) => (interp_entry, interp_base),
+                Err(err) => {
+                    println!("failed to load interpreter {}: {}", path, err);
+                    return None;
+                }
+            },
+            None => {
+                // 加载到的位置与链接时不同，需要进行重定位
+                if let Err(err) = elf.relocate(&mut memory_set, base) {
+                    println!("failed to relocate elf: {}", err);
+                    return None;
+                }
+                (entry_addr, 0)
+            },
+        };
+        // 创建新的进程
+        let mut process = Process::new(memory_set);
+        // 用户堆紧接在各段之后
+        process.init_heap(base + elf.end_address());
+
+        // 创建用户栈，主线程的用户栈固定在 USER_STACK_OFFSET 处
+        let ustack_top = process.alloc_ustack();
+        // 在用户栈上布置参数、环境变量与辅助向量
+        let mut auxv = BTreeMap::new();
+        auxv.insert(AT_PHDR, base + elf.phdr_address());
+        auxv.insert(AT_PHENT, elf.header.pt2.ph_entry_size() as usize);
+        auxv.insert(AT_PHNUM, elf.header.pt2.ph_count() as usize);
+        auxv.insert(AT_PAGESZ, PAGE_SIZE);
+        auxv.insert(AT_BASE, interp_base);
+        auxv.insert(AT_ENTRY, entry_addr);
+        // 目前只有一个用户，各种 id 均为 0
+        auxv.insert(AT_UID, 0);
+        auxv.insert(AT_EUID, 0);
+        auxv.insert(AT_GID, 0);
+        auxv.insert(AT_EGID, 0);
+        auxv.insert(AT_SECURE, 0);
+        let init_info = ProcInitInfo { args, envs, auxv };
+        let ustack_top = match init_info.push_at(&mut process.vm, ustack_top) {
+            Some(ustack_top) => ustack_top,
+            None => {
+                println!("arguments too long!");
+                return None;
+            }
         };
 
         // 创建内核栈
         let kstack = KernelStack::new();
 
+        let thread = Box::new(
+            Thread {
+                context: Context::new_user_thread(start_addr, ustack_top, kstack.top(), process.vm.token()),
+                kstack: kstack,
+                proc: Some(Arc::new(Mutex::new(process))),
+                ustack: None,
+            }
+        );
+        // 同时通过 a0, a1, a2 传入 argc, argv, envp
+        // 它们在栈上的位置见 ProcInitInfo::push_at
+        let argc = init_info.args.len();
+        thread.append_initial_arguments([
+            argc,
+            ustack_top + 8,
+            ustack_top + 8 * (argc + 2),
+        ]);
+        Some(thread)
+    }
+    // 复制当前线程，tf 为当前线程进入内核时保存的 TrapFrame
+    // 新线程属于一个新的进程，其虚拟内存空间与打开的文件均复制自当前进程
+    pub fn fork(&self, tf: &TrapFrame) -> Box<Thread> {
+        let mut proc = self.proc
+            .as_ref()
+            .expect("kernel thread cannot fork!")
+            .lock();
+        let process = Process {
+            pid: None,
+            vm: proc.vm.clone(),
+            files: proc.files.clone(),
+            heap_start: proc.heap_start,
+            brk: proc.brk,
+        };
+        let kstack = KernelStack::new();
         Box::new(
             Thread {
-                context: Context::new_user_thread(entry_addr, ustack_top, kstack.top(), vm.token()),
+                context: unsafe { Context::new_fork(tf, kstack.top(), process.vm.token()) },
                 kstack: kstack,
-                wait: wait_thread,
+                proc: Some(Arc::new(Mutex::new(process))),
+                ustack: None,
             }
         )
     }
+    // 在当前线程所属的进程中新建一个线程，其入口点为 entry ，并传入初始参数 args
+    // 新线程拥有自己的用户栈
+    pub fn new_thread(&self, entry: usize, args: [usize; 3]) -> Box<Thread> {
+        let proc = self.proc
+            .as_ref()
+            .expect("kernel thread cannot create user thread!");
+        let (ustack_top, token) = {
+            let mut process = proc.lock();
+            (process.alloc_ustack(), process.vm.token())
+        };
+        let kstack = KernelStack::new();
+        let thread = Box::new(
+            Thread {
+                context: unsafe { Context::new_user_thread(entry, ustack_top, kstack.top(), token) },
+                kstack: kstack,
+                proc: Some(proc.clone()),
+                ustack: Some(ustack_top - USER_STACK_SIZE),
+            }
+        );
+        thread.append_initial_arguments(args);
+        thread
+    }
+    // 线程退出前调用，删除其单独使用的用户栈，之后新建的线程可以重新使用这段虚拟地址
+    // 注意调用时不能持有进程的锁，也不能关闭中断，否则可能在等待其他线程释放进程的锁时死锁
+    pub fn release_ustack(&self) {
+        if let (Some(proc), Some(bottom)) = (self.proc.as_ref(), self.ustack) {
+            proc.lock().vm.remove(bottom, bottom + USER_STACK_SIZE);
+        }
+    }
     // 为线程传入初始参数
     pub fn append_initial_arguments(&self, args: [usize; 3]) {
         unsafe { self.context.append_initial_arguments(args); }
@@ -145,37 +361,362 @@ pub enum Status {
     Exited(ExitCode),
 }
 
+// 辅助向量 (auxv) 中用到的类型，取值与 Linux 一致
+pub const AT_NULL: u8 = 0;
+pub const AT_PHDR: u8 = 3;
+pub const AT_PHENT: u8 = 4;
+pub const AT_PHNUM: u8 = 5;
+pub const AT_PAGESZ: u8 = 6;
+pub const AT_BASE: u8 = 7;
+pub const AT_ENTRY: u8 = 9;
+pub const AT_UID: u8 = 11;
+pub const AT_EUID: u8 = 12;
+pub const AT_GID: u8 = 13;
+pub const AT_EGID: u8 = 14;
+pub const AT_SECURE: u8 = 23;
+pub const AT_RANDOM: u8 = 25;
+
+// 用户程序启动时得到的参数、环境变量与辅助向量
+pub struct ProcInitInfo {
+    pub args: Vec<String>,
+    pub envs: Vec<String>,
+    pub auxv: BTreeMap<u8, usize>,
+}
+
+impl ProcInitInfo {
+    // 按照 RISC-V System V ABI 的约定，将参数与环境变量布置在栈顶为 stack_top 的用户栈上
+    // 返回新的栈顶，此时从栈顶开始依次为：
+    // argc, argv[0..argc], 0, envp[..], 0, auxv[..], AT_NULL, 以及各字符串的内容
+    // 用户栈放不下时返回 None
+    pub fn push_at(&self, vm: &mut MemorySet, stack_top: usize) -> Option<usize> {
+        let mut writer = StackWriter { vm, sp: stack_top, bottom: stack_top - USER_STACK_SIZE };
+        // 先放字符串本身
+        let envs = self.envs.iter().map(|env| writer.push_str(env)).collect::<Option<Vec<usize>>>()?;
+        let argv = self.args.iter().map(|arg| writer.push_str(arg)).collect::<Option<Vec<usize>>>()?;
+        // AT_RANDOM 指向栈上的 16 个字节，musl 等 libc 用它来初始化栈保护的 canary
+        // 这里用启动时的时钟周期数凑出这 16 个字节
+        let mut auxv = self.auxv.clone();
+        let cycle = get_cycle();
+        let random = writer.push_slice(&[cycle, cycle.rotate_left(32) ^ 0x9e3779b97f4a7c15])?;
+        auxv.insert(AT_RANDOM, random);
+        // 保证最终的栈顶 16 字节对齐
+        let words = argv.len() + envs.len() + 3 + 2 * (auxv.len() + 1);
+        writer.sp = (writer.sp.checked_sub(words * 8)? & !0xf) + words * 8;
+        // auxv: 以 AT_NULL 结尾的 (类型, 值) 对
+        writer.push_slice(&[AT_NULL as usize, 0])?;
+        for (&key, &value) in auxv.iter().rev() {
+            writer.push_slice(&[key as usize, value])?;
+        }
+        // envp
+        writer.push_slice(&[0usize])?;
+        writer.push_slice(envs.as_slice())?;
+        // argv
+        writer.push_slice(&[0usize])?;
+        writer.push_slice(argv.as_slice())?;
+        // argc
+        writer.push_slice(&[argv.len()])?;
+        Some(writer.sp)
+    }
+}
+
+// 自顶向下地向另一个虚拟内存空间中的用户栈写入数据，不能低于栈底 bottom
+struct StackWriter<'a> {
+    vm: &'a mut MemorySet,
+    sp: usize,
+    bottom: usize,
+}
+
+impl StackWriter<'_> {
+    // 压入一个数组，返回其起始地址，超出用户栈时返回 None
+    fn push_slice<T: Copy>(&mut self, values: &[T]) -> Option<usize> {
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                values.as_ptr() as *const u8,
+                values.len() * core::mem::size_of::<T>(),
+            )
+        };
+        if self.sp < self.bottom + bytes.len() {
+            return None;
+        }
+        self.sp -= bytes.len();
+        if !self.vm.write_at(self.sp, bytes) {
+            return None;
+        }
+        Some(self.sp)
+    }
+    // 压入一个以 \0 结尾的字符串，返回其起始地址
+    fn push_str(&mut self, s: &str) -> Option<usize> {
+        self.push_slice(&[0u8])?;
+        self.push_slice(s.as_bytes())
+    }
+}
+
+// 将动态链接程序的解释器 path 加载到 memory_set 中 mmap 使用的区域，返回其基址与入口点
+// 解释器会自行完成重定位，这里不需要处理
+fn load_interpreter(memory_set: &mut MemorySet, path: &str) -> Result<(usize, usize), &'static str> {
+    let data = ROOT_INODE
+        .lookup(path)
+        .map_err(|_| "file not found")?
+        .read_as_vec()
+        .map_err(|_| "failed to read file")?;
+    let elf = ElfFile::new(data.as_slice())?;
+    if elf.header.pt2.type_().as_type() != header::Type::SharedObject {
+        return Err("not a shared object");
+    }
+    if elf.interpreter().is_some() {
+        return Err("interpreter requires another interpreter");
+    }
+    let base = memory_set
+        .find_free_area(USER_MMAP_OFFSET, elf.end_address())
+        .ok_or("no space for interpreter")?;
+    elf.map_segments(memory_set, base)?;
+    Ok((base, base.wrapping_add(elf.header.pt2.entry_point() as usize)))
+}
+
+// 动态段中用到的表项类型
+const DT_NULL: usize = 0;
+const DT_RELA: usize = 7;
+const DT_RELASZ: usize = 8;
+const DT_RELAENT: usize = 9;
+// 重定位类型
+const R_RISCV_NONE: usize = 0;
+const R_RISCV_RELATIVE: usize = 3;
+// Elf64_Rela 的大小
+const RELA_SIZE: usize = 24;
+
+// 以小端序读出 8 字节
+fn read_u64(bytes: &[u8]) -> usize {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf) as usize
+}
+
 trait ElfExt {
-    fn make_memory_set(&self) -> MemorySet;
+    fn make_memory_set(&self, base: usize) -> Result<MemorySet, &'static str>;
+    fn map_segments(&self, memory_set: &mut MemorySet, base: usize) -> Result<(), &'static str>;
+    fn interpreter(&self) -> Option<&str>;
+    fn relocate(&self, memory_set: &mut MemorySet, base: usize) -> Result<(), &'static str>;
+    fn end_address(&self) -> usize;
+    fn phdr_address(&self) -> usize;
+    fn vaddr_to_offset(&self, vaddr: usize) -> Option<usize>;
+    fn is_loaded(&self, vaddr: usize, len: usize) -> bool;
 }
-// 给一个用户程序的ELF可执行文件创建虚拟内存空间
+// 给一个用户程序的ELF可执行文件创建虚拟内存空间，各段加载到 base 加上其虚拟地址处
 impl ElfExt for ElfFile<'_> {
-    fn make_memory_set(&self) -> MemorySet {
+    fn make_memory_set(&self, base: usize) -> Result<MemorySet, &'static str> {
         // MemorySet::new()的实现中已经映射了内核各数据、代码段，以及物理内存段
         // 于是我们只需接下来映射用户程序各段即可
         let mut memory_set = MemorySet::new();
+        self.map_segments(&mut memory_set, base)?;
+        Ok(memory_set)
+    }
+    // 将各 LOAD 段映射到 memory_set 中
+    fn map_segments(&self, memory_set: &mut MemorySet, base: usize) -> Result<(), &'static str> {
         for ph in self.program_iter() {
             // 遍历各段并依次尝试插入 memory_set
             if ph.get_type() != Ok(Type::Load) {
                 continue;
             }
-            let vaddr = ph.virtual_addr() as usize;
-            let mem_size = ph.mem_size() as usize;
-            let data = match ph.get_data(self).unwrap() {
-                SegmentData::Undefined(data) => data,
-                _ => unreachable!(),
+            // 同时可写可执行的段容易被利用来执行注入的代码，拒绝加载
+            if ph.flags().is_write() && ph.flags().is_execute() {
+                return Err("segment is both writable and executable");
+            }
+            // 段的位置来自文件，需要检查是否溢出以及是否位于用户地址空间中
+            let vaddr = base
+                .checked_add(ph.virtual_addr() as usize)
+                .ok_or("segment address overflow")?;
+            let end = vaddr
+                .checked_add(ph.mem_size() as usize)
+                .filter(|&end| end <= USER_STACK_OFFSET)
+                .ok_or("segment out of user address space")?;
+            let attr = ph.flags().to_attr(); //将elf段的标志转化为我们熟悉的 MemoryAttr
+            // 不使用 get_data ，它在段的内容超出文件范围时会直接 panic
+            let offset = ph.offset() as usize;
+            let file_size = ph.file_size() as usize;
+            if file_size > ph.mem_size() as usize {
+                return Err("segment file size exceeds memory size");
+            }
+            // 文件中没有内容的段（如只有 .bss ）不必检查 offset
+            let mut data = if file_size == 0 {
+                &[]
+            } else {
+                offset
+                    .checked_add(file_size)
+                    .and_then(|end| self.input.get(offset..end))
+                    .ok_or("segment data out of range")?
             };
-            // 这里在插入一个 MemoryArea 时还需要复制数据
-            // 所以我们将 MemorySet 的接口略作修改，最后一个参数为数据源
-            memory_set.push(
-                vaddr,
-                vaddr + mem_size,
-                ph.flags().to_attr(), //将elf段的标志转化为我们熟悉的 MemoryAttr
-                ByFrame::new(),
-                Some((data.as_ptr() as usize, data.len())),
-            );
+            // 文件中的内容之后直到 mem_size 的部分（如 .bss）需要清零
+            // 与文件内容位于同一页的部分交给 page_copy 在复制时完成，其后的整页交给 Lazy
+            let mut start = vaddr;
+            // vaddr 不是页对齐的，其所在的页可能已经属于上一个段
+            // 此时直接将这一页中属于本段的内容写入，这一页的权限为两个段的权限之并
+            let page = vaddr / PAGE_SIZE * PAGE_SIZE;
+            if vaddr < end && !memory_set.test_free_area(vaddr, vaddr + 1) {
+                let shared_attr = memory_set
+                    .attr_at(page)
+                    .ok_or("segments overlap")?
+                    .union(&attr);
+                if shared_attr.writable() && shared_attr.executable() {
+                    return Err("shared page is both writable and executable");
+                }
+                let page_end = (page + PAGE_SIZE).min(end);
+                let length = (page_end - vaddr).min(data.len());
+                if !memory_set.write_at(vaddr, &data[..length])
+                    || !memory_set.write_at(vaddr + length, &[0u8; PAGE_SIZE][..page_end - vaddr - length]) {
+                    return Err("failed to write segment");
+                }
+                if !memory_set.protect(page, page + PAGE_SIZE, shared_attr) {
+                    return Err("segments overlap");
+                }
+                start = page_end;
+                data = &data[length..];
+            }
+            if start == end {
+                continue;
+            }
+            if !memory_set.test_free_area(start, end) {
+                return Err("segments overlap");
+            }
+            // 包含文件内容的页立即分配物理页帧并复制
+            let data_end = ((start + data.len() + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE).min(end);
+            if start < data_end {
+                // 这里在插入一个 MemoryArea 时还需要复制数据
+                // 所以我们将 MemorySet 的接口略作修改，最后一个参数为数据源
+                memory_set.push(
+                    start,
+                    data_end,
+                    attr.clone(),
+                    CopyOnWrite::new(),
+                    Some((data.as_ptr() as usize, data.len())),
+                );
+            }
+            // 之后全为 0 的页等到访问时再分配，很大的 .bss 因此不会在加载时耗尽物理页帧
+            if data_end < end {
+                memory_set.push(data_end, end, attr, Lazy::new(), None);
+            }
         }
-        memory_set
+        Ok(())
+    }
+    // PT_INTERP 段中给出的解释器路径，没有该段时为 None
+    fn interpreter(&self) -> Option<&str> {
+        let ph = self.program_iter().find(|ph| ph.get_type() == Ok(Type::Interp))?;
+        let start = ph.offset() as usize;
+        let path = self.input.get(start..start.checked_add(ph.file_size() as usize)?)?;
+        // 去掉结尾的 \0
+        let path = match path.iter().position(|&ch| ch == 0) {
+            Some(len) => &path[..len],
+            None => path,
+        };
+        str::from_utf8(path).ok()
+    }
+    // 根据动态段中的重定位表，修改加载到 base 处的各段的内容
+    // 目前只支持 R_RISCV_RELATIVE ，即将 base + addend 写入 base + offset 处
+    // 这已足以运行不依赖共享库的 PIE
+    fn relocate(&self, memory_set: &mut MemorySet, base: usize) -> Result<(), &'static str> {
+        let dynamic = match self.program_iter().find(|ph| ph.get_type() == Ok(Type::Dynamic)) {
+            Some(ph) => ph,
+            // 没有动态段，不需要重定位
+            None => return Ok(()),
+        };
+        let start = dynamic.offset() as usize;
+        let data = start
+            .checked_add(dynamic.file_size() as usize)
+            .and_then(|end| self.input.get(start..end))
+            .ok_or("dynamic segment out of range")?;
+        // 动态段由 (d_tag, d_val) 组成，以 DT_NULL 结尾
+        let (mut rela, mut rela_size, mut rela_entry) = (0, 0, RELA_SIZE);
+        for entry in data.chunks_exact(16) {
+            let value = read_u64(&entry[8..]);
+            match read_u64(entry) {
+                DT_NULL => break,
+                DT_RELA => rela = value,
+                DT_RELASZ => rela_size = value,
+                DT_RELAENT => rela_entry = value,
+                _ => {},
+            }
+        }
+        if rela_size == 0 {
+            return Ok(());
+        }
+        if rela_entry < RELA_SIZE {
+            return Err("bad relocation entry size");
+        }
+        let start = self.vaddr_to_offset(rela).ok_or("relocation table not loaded")?;
+        let table = start
+            .checked_add(rela_size)
+            .and_then(|end| self.input.get(start..end))
+            .ok_or("relocation table out of range")?;
+        // 每个 Elf64_Rela 依次为 r_offset, r_info, r_addend
+        for entry in table.chunks_exact(rela_entry) {
+            let offset = read_u64(entry);
+            let info = read_u64(&entry[8..]);
+            let addend = read_u64(&entry[16..]);
+            match info & 0xffffffff {
+                R_RISCV_NONE => {},
+                R_RISCV_RELATIVE => {
+                    // 要修改的位置来自文件，必须落在已经加载的段中
+                    if !self.is_loaded(offset, 8) {
+                        return Err("relocation target out of range");
+                    }
+                    let value = base.wrapping_add(addend);
+                    if !memory_set.write_at(base + offset, &value.to_le_bytes()) {
+                        return Err("failed to write relocation");
+                    }
+                },
+                _ => return Err("unsupported relocation type"),
+            }
+        }
+        Ok(())
+    }
+    // 各段结束地址的最大值，向上对齐到页
+    fn end_address(&self) -> usize {
+        let end = self.program_iter()
+            .filter(|ph| ph.get_type() == Ok(Type::Load))
+            .map(|ph| ph.virtual_addr().saturating_add(ph.mem_size()) as usize)
+            .max()
+            .unwrap_or(0);
+        // 不合法的段会在 map_segments 中被拒绝，这里只需避免溢出
+        end.saturating_add(PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE
+    }
+    // 程序头表被加载到的虚拟地址，作为 AT_PHDR 传给用户程序
+    // 优先使用 PT_PHDR 段，否则在包含程序头表的 LOAD 段中换算
+    fn phdr_address(&self) -> usize {
+        let ph_offset = self.header.pt2.ph_offset();
+        for ph in self.program_iter() {
+            if ph.get_type() == Ok(Type::Phdr) {
+                return ph.virtual_addr() as usize;
+            }
+        }
+        for ph in self.program_iter() {
+            if ph.get_type() == Ok(Type::Load)
+                && ph.offset() <= ph_offset
+                && ph_offset - ph.offset() < ph.file_size() {
+                return ph.virtual_addr().wrapping_add(ph_offset - ph.offset()) as usize;
+            }
+        }
+        0
+    }
+    // 由虚拟地址找到 LOAD 段中对应的内容在文件中的偏移
+    fn vaddr_to_offset(&self, vaddr: usize) -> Option<usize> {
+        self.program_iter()
+            .filter(|ph| ph.get_type() == Ok(Type::Load))
+            .find(|ph| {
+                let start = ph.virtual_addr() as usize;
+                start <= vaddr && vaddr - start < ph.file_size() as usize
+            })
+            .and_then(|ph| (ph.offset() as usize).checked_add(vaddr - ph.virtual_addr() as usize))
+    }
+    // 未加上基址的虚拟地址区间 [vaddr, vaddr + len) 是否完全落在某个 LOAD 段中
+    fn is_loaded(&self, vaddr: usize, len: usize) -> bool {
+        self.program_iter()
+            .filter(|ph| ph.get_type() == Ok(Type::Load))
+            .any(|ph| {
+                let start = ph.virtual_addr() as usize;
+                start <= vaddr
+                    && vaddr
+                        .checked_add(len)
+                        .map_or(false, |end| end - start <= ph.mem_size() as usize)
+            })
     }
 }
 
@@ -186,9 +727,16 @@ trait ToMemoryAttr {
 impl ToMemoryAttr for Flags {
     fn to_attr(&self) -> MemoryAttr {
         let mut flags = MemoryAttr::new().set_user();
+        if !self.is_write() {
+            flags = flags.set_readonly();
+        }
         if self.is_execute() {
             flags = flags.set_execute();
         }
+        // RISC-V 的页表项无法表示可写但不可读的页，可写或可执行的页总是可读的
+        if !self.is_read() && !self.is_write() && !self.is_execute() {
+            flags = flags.set_no_access();
+        }
         flags
     }
-}
\ No newline at end of file
+}
@@ -3,8 +3,10 @@ use crate::process::structs::*;
 use crate::alloc::{
     vec::Vec,
     boxed::Box,
+    sync::Arc,
 };
-use crate::process::Tid;
+use crate::process::{ Tid, ExitCode, Waiter };
+use spin::Mutex;
 
 // 线程池每个位置的信息
 pub struct ThreadInfo {
@@ -12,6 +14,18 @@ pub struct ThreadInfo {
     pub status: Status,
     // 占据这个位置的线程
     pub thread: Option<Box<Thread>>,
+    // 父线程，其退出码将由父线程回收
+    pub parent: Option<Tid>,
+    // 是否正在等待子线程退出
+    pub waiting: bool,
+    // 是否跟踪这个线程的系统调用
+    pub trace: bool,
+    // 是否跟踪这个线程此后创建的线程的系统调用
+    pub trace_children: bool,
+    // 被同一进程中的其他线程通过 exit_group 结束时的退出码
+    pub killed: Option<ExitCode>,
+    // 加入线程池时分配的编号，各线程互不相同，见 Waiter
+    pub generation: usize,
 }
 
 pub struct ThreadPool {
@@ -22,6 +36,8 @@ pub struct ThreadPool {
     // 这里的 dyn Scheduler 是 Trait object 语法
     // 表明 Box 里面的类型实现了 Scheduler Trait
     scheduler: Box<dyn Scheduler>,
+    // 下一个加入线程池的线程的编号
+    next_generation: usize,
 }
 
 impl ThreadPool {
@@ -34,6 +50,7 @@ impl ThreadPool {
                 v
             },
             scheduler,
+            next_generation: 0,
         }
     }
     // 在线程池中找一个编号最小的空着的位置
@@ -47,11 +64,18 @@ impl ThreadPool {
         panic!("alloc tid failed!");
     }
 
-    // 加入一个可立即开始运行的线程
+    // 加入一个可立即开始运行的线程，其父线程为 parent
     // 线程状态 Uninitialized -> Ready
-    pub fn add(&mut self, _thread: Box<Thread>) {
+    // 返回分配给该线程的 Tid
+    pub fn add(&mut self, _thread: Box<Thread>, parent: Option<Tid>) -> Tid {
         // 分配 Tid
         let tid = self.alloc_tid();
+        // 被跟踪线程创建的线程同样被跟踪
+        let trace = parent
+            .and_then(|parent| self.threads[parent].as_ref())
+            .map_or(false, |info| info.trace || info.trace_children);
+        let generation = self.next_generation;
+        self.next_generation += 1;
         // 修改线程池对应位置的信息
         self.threads[tid] = Some(
             ThreadInfo {
@@ -59,27 +83,39 @@ impl ThreadPool {
                 status: Status::Ready,
                 // 传入线程
                 thread: Some(_thread),
+                parent,
+                waiting: false,
+                trace,
+                trace_children: false,
+                killed: None,
+                generation,
             }
         );
         // 将线程的 Tid 加入调度器
         // 提醒调度器给这个线程分配 CPU 资源
         self.scheduler.push(tid);
+        tid
     }
 
     // 从线程池中取一个线程开始运行
     // 线程状态 Ready -> Running
     pub fn acquire(&mut self) -> Option<(Tid, Box<Thread>)> {
         // 调用 Scheduler::pop ，从调度算法中获取接下来要运行的 Tid
-        if let Some(tid) = self.scheduler.pop() {
+        while let Some(tid) = self.scheduler.pop() {
             // 获取并更新线程池对应位置的信息
             let mut thread_info = self.threads[tid].as_mut().expect("thread not exist!");
+            // 已被 exit_group 结束的线程不再运行，在这里完成退出并将其释放
+            if let Some(code) = thread_info.killed {
+                let thread = thread_info.thread.take();
+                self.exit(tid, code);
+                drop(thread);
+                continue;
+            }
             // 将线程状态改为 Running
             thread_info.status = Status::Running(tid);
             return Some((tid, thread_info.thread.take().expect("thread not exist!")));
         }
-        else {
-            return None;
-        }
+        None
     }
     // 这个线程已运行了太长时间或者已运行结束，需要交出CPU资源
     // 但是要提醒线程池它仍需要分配 CPU 资源
@@ -91,6 +127,11 @@ impl ThreadPool {
         }
         // 获取并修改线程池对应位置的信息
         let mut thread_info = self.threads[tid].as_mut().expect("thread not exist!");
+        // 线程已经退出，只是退出码还没有被父线程回收
+        // 线程本身已经不再需要，直接将其释放
+        if let Status::Exited(_) = thread_info.status {
+            return;
+        }
         thread_info.thread = Some(thread);
         // 此时状态可能是 Status::Sleeping(线程可能会自动放弃 CPU 资源，进入睡眠状态),
         // 直到被唤醒之前都不必给它分配。
@@ -108,15 +149,105 @@ impl ThreadPool {
         ret
     }
     // 这个线程已经退出了，线程状态 Running -> Exited
-    pub fn exit(&mut self, tid: Tid) {
-        // 清空线程池对应位置
-        self.threads[tid] = None;
+    pub fn exit(&mut self, tid: Tid, code: ExitCode) {
+        // 它的子线程不再有父线程
+        // 其中已经退出的子线程再也不会被回收，直接清空其位置
+        for i in 0..self.threads.len() {
+            let exited = match self.threads[i].as_mut() {
+                Some(info) if info.parent == Some(tid) => {
+                    info.parent = None;
+                    if let Status::Exited(_) = info.status { true } else { false }
+                },
+                _ => false,
+            };
+            if exited {
+                self.threads[i] = None;
+            }
+        }
+        let parent = self.threads[tid].as_ref().expect("thread not exist when exiting").parent;
+        match parent {
+            Some(parent) => {
+                // 保留线程池对应位置，等待父线程回收退出码
+                self.threads[tid].as_mut().unwrap().status = Status::Exited(code);
+                // 如果父线程正在等待子线程退出，将其唤醒
+                let parent_info = self.threads[parent].as_mut().expect("parent not exist!");
+                if parent_info.waiting {
+                    parent_info.waiting = false;
+                    let generation = parent_info.generation;
+                    self.wakeup(Waiter { tid: parent, generation });
+                }
+            },
+            None => {
+                // 没有父线程，清空线程池对应位置
+                self.threads[tid] = None;
+            }
+        }
         // 通知调度器
         self.scheduler.exit(tid);
     }
-    pub fn wakeup(&mut self, tid: Tid) {
-        let proc = self.threads[tid].as_mut().expect("thread not exist when waking up");
+    // 结束进程 proc 中除了线程 tid 之外的全部线程，退出码均为 code
+    // 这些线程不会再运行，而是在下一次被调度时由 acquire 完成退出
+    // 正在睡眠的线程因此需要先被唤醒
+    pub fn kill_others(&mut self, tid: Tid, proc: &Arc<Mutex<Process>>, code: ExitCode) {
+        for i in 0..self.threads.len() {
+            if i == tid {
+                continue;
+            }
+            let info = match self.threads[i].as_mut() {
+                Some(info) => info,
+                None => continue,
+            };
+            let same_process = info.thread
+                .as_ref()
+                .and_then(|thread| thread.proc.as_ref())
+                .map_or(false, |other| Arc::ptr_eq(other, proc));
+            if !same_process || info.killed.is_some() {
+                continue;
+            }
+            info.killed = Some(code);
+            if let Status::Sleeping = info.status {
+                info.status = Status::Ready;
+                self.scheduler.push(i);
+            }
+        }
+    }
+    // 父线程 parent 回收一个已经退出的子线程，tid 为 None 表示任意一个子线程
+    // 成功则清空其位置并返回其 Tid 与退出码
+    // 返回 Ok(None) 表示符合条件的子线程都还没有退出
+    // 返回 Err(()) 表示没有符合条件的子线程
+    pub fn wait(&mut self, parent: Tid, tid: Option<Tid>) -> Result<Option<(Tid, ExitCode)>, ()> {
+        let mut found = false;
+        for (i, info) in self.threads.iter().enumerate() {
+            if let Some(info) = info {
+                if info.parent != Some(parent) || tid.map_or(false, |tid| tid != i) {
+                    continue;
+                }
+                found = true;
+                if let Status::Exited(code) = info.status {
+                    self.threads[i] = None;
+                    return Ok(Some((i, code)));
+                }
+            }
+        }
+        if found { Ok(None) } else { Err(()) }
+    }
+    // Scheduler 的简单包装：设置线程的优先级
+    pub fn set_priority(&mut self, tid: Tid, priority: usize) {
+        self.scheduler.set_priority(tid, priority);
+    }
+    // 唤醒线程 waiter ，返回是否成功
+    // 等待队列中可能残留已经退出的线程，其位置可能已经为空，甚至 Tid 已经分配给了新线程，此时直接忽略
+    // 被 exit_group 结束的线程已经被唤醒过，同样忽略
+    pub fn wakeup(&mut self, waiter: Waiter) -> bool {
+        let proc = match self.threads[waiter.tid].as_mut() {
+            Some(proc) if proc.generation == waiter.generation && proc.killed.is_none() => proc,
+            _ => return false,
+        };
+        if let Status::Exited(_) = proc.status {
+            return false;
+        }
         proc.status = Status::Ready;
-        self.scheduler.push(tid);
+        self.scheduler.push(waiter.tid);
+        true
     }
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+// 系统调用跟踪：类似 Linux 下的 strace
+// 被跟踪的线程每进行一次系统调用，就打印其名称、解析后的参数与返回值
+use crate::syscall::*;
+use crate::memory::user::{ copy_from_user, read_user_cstr };
+use alloc::string::String;
+use core::fmt::Write;
+
+// 字符串与缓冲区最多显示的字节数
+const MAX_SHOWN: usize = 32;
+
+// 参数的解析方式
+#[derive(Clone, Copy)]
+enum Arg {
+    // 有符号十进制整数，包括文件描述符
+    Int,
+    // 十六进制整数，如地址、标志位
+    Hex,
+    // 以 \0 结尾的字符串
+    Str,
+    // 缓冲区，其长度为下一个参数
+    Buf,
+}
+
+use Arg::*;
+
+// 系统调用的名称以及各参数的解析方式
+fn signature(id: usize) -> Option<(&'static str, &'static [Arg])> {
+    let sig: (&'static str, &'static [Arg]) = match id {
+        SYS_FCNTL => ("fcntl", &[Int, Int, Hex]),
+        SYS_IOCTL => ("ioctl", &[Int, Hex, Hex]),
+        SYS_OPENAT => ("openat", &[Int, Str, Hex, Hex]),
+        SYS_CLOSE => ("close", &[Int]),
+        SYS_READ => ("read", &[Int, Hex, Int]),
+        SYS_WRITE => ("write", &[Int, Buf, Int]),
+        SYS_WRITEV => ("writev", &[Int, Hex, Int]),
+        SYS_EXIT => ("exit", &[Int]),
+        SYS_EXIT_GROUP => ("exit_group", &[Int]),
+        SYS_SET_TID_ADDRESS => ("set_tid_address", &[Hex]),
+        SYS_NANOSLEEP => ("nanosleep", &[Hex]),
+        SYS_CLOCK_GETTIME => ("clock_gettime", &[Int, Hex]),
+        SYS_UNAME => ("uname", &[Hex]),
+        SYS_GETPID => ("getpid", &[]),
+        SYS_GETTID => ("gettid", &[]),
+        SYS_BRK => ("brk", &[Hex]),
+        SYS_MUNMAP => ("munmap", &[Hex, Int]),
+        SYS_FORK => ("fork", &[]),
+        SYS_EXEC => ("exec", &[Str, Hex, Hex]),
+        SYS_MMAP => ("mmap", &[Hex, Int, Hex, Hex, Int, Hex]),
+        SYS_MPROTECT => ("mprotect", &[Hex, Int, Hex]),
+        SYS_WAIT4 => ("wait4", &[Int, Hex, Hex]),
+        SYS_THREAD_CREATE => ("thread_create", &[Hex, Int, Int]),
+        SYS_TRACE => ("trace", &[Int]),
+        SYS_SET_PRIORITY => ("set_priority", &[Int]),
+        _ => return None,
+    };
+    Some(sig)
+}
+
+// 将一段字节按照 C 语言字符串的形式写入 out ，不可打印的字符进行转义
+fn write_escaped(out: &mut String, bytes: &[u8], truncated: bool) {
+    out.push('"');
+    for &ch in bytes {
+        match ch {
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(ch as char),
+            _ => { write!(out, "\\x{:02x}", ch).unwrap(); },
+        }
+    }
+    out.push('"');
+    if truncated {
+        out.push_str("...");
+    }
+}
+
+// 按照 arg 描述的方式解析参数 value ，next 为下一个参数
+fn write_arg(out: &mut String, arg: Arg, value: usize, next: usize) {
+    match arg {
+        Int => { write!(out, "{}", value as isize).unwrap(); },
+        Hex => { write!(out, "{:#x}", value).unwrap(); },
+        Str => match read_user_cstr(value) {
+            Ok(s) => write_escaped(out, &s.as_bytes()[..s.len().min(MAX_SHOWN)], s.len() > MAX_SHOWN),
+            // 地址不合法时只显示地址
+            Err(_) => { write!(out, "{:#x}", value).unwrap(); },
+        },
+        Buf => {
+            let mut buf = [0u8; MAX_SHOWN];
+            let len = next.min(MAX_SHOWN);
+            match copy_from_user(&mut buf[..len], value) {
+                Ok(()) => write_escaped(out, &buf[..len], next > MAX_SHOWN),
+                Err(_) => { write!(out, "{:#x}", value).unwrap(); },
+            }
+        },
+    }
+}
+
+// 将系统调用格式化为 name(arg0, arg1, ...) 的形式
+pub fn format_call(id: usize, args: &[usize; 6]) -> String {
+    let mut out = String::new();
+    match signature(id) {
+        Some((name, sig)) => {
+            out.push_str(name);
+            out.push('(');
+            for (i, &arg) in sig.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_arg(&mut out, arg, args[i], args.get(i + 1).cloned().unwrap_or(0));
+            }
+            out.push(')');
+        },
+        // 不认识的系统调用显示编号与全部参数
+        None => {
+            write!(out, "syscall_{}({:#x}, {:#x}, {:#x}, {:#x}, {:#x}, {:#x})",
+                id, args[0], args[1], args[2], args[3], args[4], args[5]).unwrap();
+        },
+    }
+    out
+}
+
+// 错误码的名称
+fn errno_name(errno: isize) -> Option<&'static str> {
+    let name = match errno {
+        ENOENT => "ENOENT",
+        E2BIG => "E2BIG",
+        EIO => "EIO",
+        ENOEXEC => "ENOEXEC",
+        EBADF => "EBADF",
+        ECHILD => "ECHILD",
+        EAGAIN => "EAGAIN",
+        ENOMEM => "ENOMEM",
+        EFAULT => "EFAULT",
+        EINVAL => "EINVAL",
+        ENOTTY => "ENOTTY",
+        ENOSYS => "ENOSYS",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// 格式化返回值，出错时附上错误码的名称
+pub fn format_ret(ret: isize) -> String {
+    let mut out = String::new();
+    write!(out, "{}", ret).unwrap();
+    if ret < 0 {
+        if let Some(name) = errno_name(-ret) {
+            write!(out, " {}", name).unwrap();
+        }
+    }
+    out
+}
@@ -1,55 +1,711 @@
 use crate::context::TrapFrame;
-use crate::process;
+use crate::process::{ self, ExecError };
+use crate::timer;
+use crate::strace;
+use crate::memory::memory_set::{
+    attr::MemoryAttr,
+    handler::{ Lazy, FileBacked },
+};
+use crate::fs::{ ROOT_INODE, file::File, stdio::STDIN };
+use crate::consts::*;
+use crate::memory::user::{
+    copy_from_user,
+    copy_to_user,
+    read_user,
+    write_user,
+    read_user_cstr,
+    read_user_cstr_array,
+};
+use alloc::vec::Vec;
 
+pub const SYS_FCNTL: usize = 25;
+pub const SYS_IOCTL: usize = 29;
+pub const SYS_OPENAT: usize = 56;
+pub const SYS_CLOSE: usize = 57;
 pub const SYS_READ: usize = 63;
 pub const SYS_WRITE: usize = 64;
+pub const SYS_WRITEV: usize = 66;
 pub const SYS_EXIT: usize = 93;
+pub const SYS_EXIT_GROUP: usize = 94;
+pub const SYS_SET_TID_ADDRESS: usize = 96;
+pub const SYS_NANOSLEEP: usize = 101;
+pub const SYS_CLOCK_GETTIME: usize = 113;
+pub const SYS_UNAME: usize = 160;
+pub const SYS_GETPID: usize = 172;
+pub const SYS_GETTID: usize = 178;
+pub const SYS_BRK: usize = 214;
+pub const SYS_MUNMAP: usize = 215;
+pub const SYS_FORK: usize = 220;
 pub const SYS_EXEC: usize = 221;
+pub const SYS_MMAP: usize = 222;
+pub const SYS_MPROTECT: usize = 226;
+pub const SYS_WAIT4: usize = 260;
+// 非 Linux 标准：在当前进程中新建一个线程
+pub const SYS_THREAD_CREATE: usize = 1000;
+// 非 Linux 标准：开启或关闭系统调用跟踪
+pub const SYS_TRACE: usize = 1001;
+// 非 Linux 标准：设置当前线程的优先级
+// Linux 中的 setpriority 参数与语义都不同，因此不使用其编号
+pub const SYS_SET_PRIORITY: usize = 1002;
 
-pub fn syscall(id: usize, args: [usize; 3], tf: &mut TrapFrame) -> isize {
+// 系统调用失败时返回的错误码，与 Linux 相同，返回时取负
+pub const ENOENT: isize = 2;
+pub const E2BIG: isize = 7;
+pub const EIO: isize = 5;
+pub const ENOEXEC: isize = 8;
+pub const EBADF: isize = 9;
+pub const ECHILD: isize = 10;
+pub const EAGAIN: isize = 11;
+pub const ENOMEM: isize = 12;
+pub const EFAULT: isize = 14;
+pub const EINVAL: isize = 22;
+pub const ENOTTY: isize = 25;
+pub const ENOSYS: isize = 38;
+
+// wait4 的选项：子线程都还没有退出时不阻塞，直接返回 0
+pub const WNOHANG: usize = 1;
+
+// sys_trace 的参数
+// 关闭跟踪
+pub const TRACE_OFF: usize = 0;
+// 跟踪当前线程，以及它此后创建的线程
+pub const TRACE_SELF: usize = 1;
+// 只跟踪当前线程此后创建的线程，如 exec 启动的程序，不跟踪当前线程自身
+pub const TRACE_CHILDREN: usize = 2;
+
+// sys_fcntl 的 cmd 参数
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+// 文件的标志：读写时不阻塞
+pub const O_NONBLOCK: usize = 0x800;
+
+// sys_ioctl 的 request 参数：获取终端窗口大小
+pub const TIOCGWINSZ: usize = 0x5413;
+
+// sys_writev 一次至多处理的 iovec 个数
+pub const IOV_MAX: usize = 1024;
+
+// sys_mmap 的 prot 参数：映射区域的权限
+pub const PROT_READ: usize = 0x1;
+pub const PROT_WRITE: usize = 0x2;
+pub const PROT_EXEC: usize = 0x4;
+// sys_mmap 的 flags 参数
+pub const MAP_SHARED: usize = 0x1;
+pub const MAP_PRIVATE: usize = 0x2;
+pub const MAP_FIXED: usize = 0x10;
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+pub fn syscall(id: usize, args: [usize; 6], tf: &mut TrapFrame) -> isize {
+    if !process::tracing() {
+        return dispatch(id, args, tf);
+    }
+    // 先解析参数，调用结束后用户内存中的内容可能已经改变
+    let tid = process::current_tid();
+    let call = strace::format_call(id, &args);
+    if id == SYS_EXIT || id == SYS_EXIT_GROUP {
+        // 不会返回
+        println!("[tid {}] {} = ?", tid, call);
+    }
+    let ret = dispatch(id, args, tf);
+    println!("[tid {}] {} = {}", tid, call, strace::format_ret(ret));
+    ret
+}
+
+fn dispatch(id: usize, args: [usize; 6], tf: &mut TrapFrame) -> isize {
     match id {
+        SYS_FCNTL => {
+            sys_fcntl(args[0], args[1], args[2])
+        },
+        SYS_OPENAT => {
+            sys_openat(args[0], args[1] as *const u8, args[2], args[3])
+        },
+        SYS_IOCTL => {
+            sys_ioctl(args[0], args[1], args[2])
+        },
+        SYS_CLOSE => {
+            sys_close(args[0])
+        },
         SYS_READ => {
             sys_read(args[0], args[1] as *mut u8, args[2])
         },
         SYS_WRITE => {
-            print!("{}", args[0] as u8 as char);
-            0
+            sys_write(args[0], args[1] as *const u8, args[2])
+        },
+        SYS_WRITEV => {
+            sys_writev(args[0], args[1], args[2])
         },
         SYS_EXIT => {
             sys_exit(args[0]);
             0
         },
+        SYS_EXIT_GROUP => {
+            sys_exit_group(args[0]);
+            0
+        },
+        SYS_SET_TID_ADDRESS => {
+            sys_set_tid_address(args[0])
+        },
+        SYS_NANOSLEEP => {
+            sys_nanosleep(args[0] as *const TimeSpec)
+        },
+        SYS_CLOCK_GETTIME => {
+            sys_clock_gettime(args[0], args[1] as *mut TimeSpec)
+        },
+        SYS_UNAME => {
+            sys_uname(args[0] as *mut UtsName)
+        },
+        SYS_GETPID => {
+            sys_getpid()
+        },
+        SYS_GETTID => {
+            process::current_tid() as isize
+        },
+        SYS_BRK => {
+            sys_brk(args[0])
+        },
+        SYS_MUNMAP => {
+            sys_munmap(args[0], args[1])
+        },
+        SYS_FORK => {
+            sys_fork(tf)
+        },
         SYS_EXEC => {
-            sys_exec(args[0] as *const u8)
+            sys_exec(args[0] as *const u8, args[1] as *const *const u8, args[2] as *const *const u8)
+        },
+        SYS_MMAP => {
+            sys_mmap(args[0], args[1], args[2], args[3], args[4], args[5])
+        },
+        SYS_MPROTECT => {
+            sys_mprotect(args[0], args[1], args[2])
+        },
+        SYS_WAIT4 => {
+            sys_wait4(args[0] as isize, args[1] as *mut i32, args[2])
+        },
+        SYS_THREAD_CREATE => {
+            sys_thread_create(args[0], args[1], args[2])
         },
-        _ => {
-            panic!("unknown syscall id {}", id);
+        SYS_TRACE => {
+            sys_trace(args[0])
         },
+        SYS_SET_PRIORITY => {
+            process::set_priority(args[0]);
+            0
+        },
+        // 不支持的系统调用，可以通过 strace 看到其编号与参数
+        _ => -ENOSYS,
     }
 }
 
+// 从文件描述符 fd 对应的文件中读入至多 len 字节到从 base 开始的位置，返回读入的字节数
+// 对于标准输入，至少等到一个字符输入后才返回，之后只读入已经输入的字符
+// 若标准输入被设为 O_NONBLOCK ，没有已输入的字符时返回 -EAGAIN
 fn sys_read(fd: usize, base: *mut u8, len: usize) -> isize {
-    unsafe {
-        *base = crate::fs::stdio::STDIN.pop() as u8;
+    let proc = process::current_process().expect("kernel thread cannot read files!");
+    let file = match proc.lock().files.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -EBADF,
+    };
+    if len == 0 {
+        return 0;
+    }
+    match file {
+        File::Stdin { nonblock } => {
+            let mut buf = Vec::new();
+            if !nonblock {
+                buf.push(STDIN.pop() as u8);
+            }
+            while buf.len() < len {
+                match STDIN.try_pop() {
+                    Some(ch) => buf.push(ch as u8),
+                    None => break,
+                }
+            }
+            if buf.is_empty() {
+                return -EAGAIN;
+            }
+            match copy_to_user(base as usize, &buf) {
+                Ok(()) => buf.len() as isize,
+                Err(err) => err,
+            }
+        },
+        File::Stdout => -EBADF,
+        File::INode { inode, mut offset } => {
+            // 每次至多读入一页到内核中的缓冲区，再复制到用户内存
+            let mut buf = [0u8; PAGE_SIZE];
+            let mut count = 0;
+            while count < len {
+                let length = PAGE_SIZE.min(len - count);
+                let read = match inode.read_at(offset, &mut buf[..length]) {
+                    Ok(read) => read,
+                    Err(_) => return -EIO,
+                };
+                if let Err(err) = copy_to_user(base as usize + count, &buf[..read]) {
+                    return err;
+                }
+                count += read;
+                offset += read;
+                // 已经读到文件末尾
+                if read < length {
+                    break;
+                }
+            }
+            // 更新文件的读写位置
+            if let Some(Some(File::INode { offset: file_offset, .. })) = proc.lock().files.get_mut(fd) {
+                *file_offset = offset;
+            }
+            count as isize
+        },
+    }
+}
+
+// 目前只支持通过 F_GETFL, F_SETFL 查询与修改标准输入的 O_NONBLOCK 标志
+fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let proc = process::current_process().expect("kernel thread has no files!");
+    let mut proc = proc.lock();
+    let file = match proc.files.get_mut(fd) {
+        Some(Some(file)) => file,
+        _ => return -EBADF,
+    };
+    match cmd {
+        F_GETFL => match file {
+            File::Stdin { nonblock: true } => O_NONBLOCK as isize,
+            _ => 0,
+        },
+        F_SETFL => {
+            if let File::Stdin { nonblock } = file {
+                *nonblock = arg & O_NONBLOCK != 0;
+            }
+            0
+        },
+        _ => -EINVAL,
+    }
+}
+
+// 将从 base 开始的 len 字节写入文件描述符 fd 对应的文件，返回写入的字节数
+fn sys_write(fd: usize, base: *const u8, len: usize) -> isize {
+    let proc = process::current_process().expect("kernel thread cannot write files!");
+    // 注意不要在持有进程的锁时访问用户内存，缺页异常处理也需要获取这个锁
+    let file = match proc.lock().files.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -EBADF,
+    };
+    // 每次至多从用户内存复制一页到内核中的缓冲区，再写入文件
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut count = 0;
+    match file {
+        File::Stdin { .. } => -EBADF,
+        File::Stdout => {
+            while count < len {
+                let length = PAGE_SIZE.min(len - count);
+                if let Err(err) = copy_from_user(&mut buf[..length], base as usize + count) {
+                    return err;
+                }
+                for &ch in &buf[..length] {
+                    crate::io::putchar(ch as char);
+                }
+                count += length;
+            }
+            count as isize
+        },
+        File::INode { inode, mut offset } => {
+            while count < len {
+                let length = PAGE_SIZE.min(len - count);
+                if let Err(err) = copy_from_user(&mut buf[..length], base as usize + count) {
+                    return err;
+                }
+                let written = match inode.write_at(offset, &buf[..length]) {
+                    Ok(written) => written,
+                    Err(_) => return -EIO,
+                };
+                count += written;
+                offset += written;
+                if written < length {
+                    break;
+                }
+            }
+            // 更新文件的读写位置
+            if let Some(Some(File::INode { offset: file_offset, .. })) = proc.lock().files.get_mut(fd) {
+                *file_offset = offset;
+            }
+            count as isize
+        },
+    }
+}
+
+// 与 Linux 的 struct iovec 布局相同
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IoVec {
+    base: usize,
+    len: usize,
+}
+
+// 依次将 iov 开始的 iovcnt 个 iovec 描述的内存写入文件描述符 fd 对应的文件
+// 返回写入的总字节数，某一段没有写完时就停下来
+fn sys_writev(fd: usize, iov: usize, iovcnt: usize) -> isize {
+    if iovcnt > IOV_MAX {
+        return -EINVAL;
+    }
+    let mut count = 0;
+    for i in 0..iovcnt {
+        let vec: IoVec = match read_user(iov + i * core::mem::size_of::<IoVec>()) {
+            Ok(vec) => vec,
+            Err(err) => return err,
+        };
+        let written = sys_write(fd, vec.base as *const u8, vec.len);
+        if written < 0 {
+            // 已经写入了一部分时，返回已写入的字节数
+            return if count > 0 { count } else { written };
+        }
+        count += written;
+        if (written as usize) < vec.len {
+            break;
+        }
+    }
+    count
+}
+
+// 与 Linux 的 struct winsize 布局相同
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WinSize {
+    row: u16,
+    col: u16,
+    xpixel: u16,
+    ypixel: u16,
+}
+
+// 对文件描述符 fd 对应的设备进行控制
+// 目前只支持对标准输入输出查询终端窗口大小，libc 用它来判断输出是否为终端
+fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    let proc = process::current_process().expect("kernel thread has no files!");
+    let file = match proc.lock().files.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -EBADF,
+    };
+    match file {
+        File::Stdin { .. } | File::Stdout => match request {
+            TIOCGWINSZ => {
+                // 串口终端的大小无从得知，按照 80x24 处理
+                let winsize = WinSize { row: 24, col: 80, xpixel: 0, ypixel: 0 };
+                match write_user(arg, &winsize) {
+                    Ok(()) => 0,
+                    Err(err) => err,
+                }
+            },
+            _ => -EINVAL,
+        },
+        File::INode { .. } => -ENOTTY,
     }
-    return 1;
 }
 
 fn sys_exit(code: usize) {
     process::exit(code);
 }
 
-pub unsafe fn from_cstr(s: *const u8) -> &'static str {
-    use core::{ slice, str };
-    // 使用迭代器获得字符串长度
-    let len = (0usize..).find(|&i| *s.add(i) == 0).unwrap();
-    str::from_utf8(slice::from_raw_parts(s, len)).unwrap()
+// 结束当前进程中的全部线程，等待它们的线程得到的退出码均为 code
+fn sys_exit_group(code: usize) {
+    process::exit_group(code);
+}
+
+// libc 在线程退出时希望内核清空 tidptr 处的值并唤醒等待者
+// 我们不支持这一功能，只是返回当前线程的 Tid
+fn sys_set_tid_address(_tidptr: usize) -> isize {
+    process::current_tid() as isize
+}
+
+// 父线程返回子线程的 Tid ，子线程返回 0
+fn sys_fork(tf: &mut TrapFrame) -> isize {
+    process::fork(tf) as isize
+}
+
+// 与 Linux 的 struct timespec 布局相同
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TimeSpec {
+    sec: usize,
+    nsec: usize,
+}
+
+// 当前线程睡眠 req 指定的时长
+fn sys_nanosleep(req: *const TimeSpec) -> isize {
+    let req: TimeSpec = match read_user(req as usize) {
+        Ok(req) => req,
+        Err(err) => return err,
+    };
+    // 与 Linux 相同，秒数为负或纳秒数超出范围时出错
+    if (req.sec as isize) < 0 || req.nsec >= 1_000_000_000 {
+        return -EINVAL;
+    }
+    // 时间过长时饱和到最大值，相当于一直睡眠
+    let cycles = (req.sec as u64)
+        .saturating_mul(timer::CLOCK_FREQ)
+        .saturating_add(req.nsec as u64 * timer::CLOCK_FREQ / 1_000_000_000);
+    if cycles > 0 {
+        timer::sleep_until(timer::get_cycle().saturating_add(cycles));
+    }
+    0
+}
+
+// sys_clock_gettime 的 clockid 参数
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+// 将时钟 clockid 的当前时间写入 tp
+// 没有实时时钟，各个时钟均从启动时开始计时
+fn sys_clock_gettime(clockid: usize, tp: *mut TimeSpec) -> isize {
+    match clockid {
+        CLOCK_REALTIME | CLOCK_MONOTONIC => {},
+        _ => return -EINVAL,
+    }
+    let cycle = timer::get_cycle();
+    let time = TimeSpec {
+        sec: (cycle / timer::CLOCK_FREQ) as usize,
+        nsec: (cycle % timer::CLOCK_FREQ * 1_000_000_000 / timer::CLOCK_FREQ) as usize,
+    };
+    match write_user(tp as usize, &time) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+// 与 Linux 的 struct utsname 布局相同，每个字段都是以 \0 结尾的字符串
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UtsName {
+    fields: [[u8; 65]; 6],
+}
+
+// 将系统的名称、版本等信息写入 buf
+fn sys_uname(buf: *mut UtsName) -> isize {
+    // 依次为 sysname, nodename, release, version, machine, domainname
+    let strings = ["rCore", "rcore", "0.1.0", "rCore-Tutorial", "riscv64", ""];
+    let mut uts = UtsName { fields: [[0; 65]; 6] };
+    for (field, s) in uts.fields.iter_mut().zip(strings.iter()) {
+        field[..s.len()].copy_from_slice(s.as_bytes());
+    }
+    match write_user(buf as usize, &uts) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+// 将用户堆的结束地址调整为 addr ，返回调整后的结束地址
+// addr 为 0 时仅查询当前的结束地址
+fn sys_brk(addr: usize) -> isize {
+    let proc = process::current_process().expect("kernel thread has no heap!");
+    let brk = proc.lock().set_brk(addr);
+    brk as isize
+}
+
+// 将 sys_mmap 的 prot 参数转化为 MemoryAttr
+fn prot_to_attr(prot: usize) -> MemoryAttr {
+    let mut attr = MemoryAttr::new().set_user();
+    if prot & (PROT_READ | PROT_WRITE | PROT_EXEC) == 0 {
+        // PROT_NONE
+        attr = attr.set_no_access();
+    }
+    if prot & PROT_WRITE == 0 {
+        attr = attr.set_readonly();
+    }
+    if prot & PROT_EXEC != 0 {
+        attr = attr.set_execute();
+    }
+    attr
+}
+
+// 打开路径为 path 的文件，返回其文件描述符
+// 路径均相对于根目录，因此忽略 dirfd ；目前也不支持 flags 与 mode
+fn sys_openat(_dirfd: usize, path: *const u8, _flags: usize, _mode: usize) -> isize {
+    let path = match read_user_cstr(path as usize) {
+        Ok(path) => path,
+        Err(err) => return err,
+    };
+    match ROOT_INODE.lookup(&path) {
+        Ok(inode) => {
+            let proc = process::current_process().expect("kernel thread cannot open files!");
+            let fd = proc.lock().add_file(File::INode { inode, offset: 0 });
+            fd as isize
+        },
+        Err(_) => -ENOENT,
+    }
 }
 
-fn sys_exec(path: *const u8) -> isize {
-    let valid = process::execute(unsafe { from_cstr(path) }, Some(process::current_tid()));
-    // 如果正常执行，则阻塞终端线程，等到启动的这个用户线程运行结束
-    if valid { process::yield_now(); }
-    // 不能正常执行，直接返回；或者被启动线程结束后唤醒终端线程之后返回
-    return 0;
+// 关闭文件描述符 fd
+fn sys_close(fd: usize) -> isize {
+    let proc = process::current_process().expect("kernel thread cannot close files!");
+    let mut proc = proc.lock();
+    match proc.files.get_mut(fd) {
+        Some(file) if file.is_some() => {
+            *file = None;
+            0
+        },
+        _ => -EBADF,
+    }
+}
+
+// 在当前进程的虚拟内存空间中新建一段长为 len 字节的映射，返回其起始地址
+// flags 包含 MAP_ANONYMOUS 时为匿名映射，目前只支持私有的匿名映射，此时忽略 fd 与 offset
+// 否则映射文件 fd 中从 offset 开始的内容，MAP_SHARED 表示修改会写回文件，MAP_PRIVATE 表示修改只对本进程可见
+// 指定 MAP_FIXED 时映射到 addr 处，原有的映射将被删除；否则 addr 仅作为参考
+fn sys_mmap(addr: usize, len: usize, prot: usize, flags: usize, fd: usize, offset: usize) -> isize {
+    if len == 0 || addr % PAGE_SIZE != 0 || offset % PAGE_SIZE != 0 {
+        return -EINVAL;
+    }
+    // MAP_SHARED 与 MAP_PRIVATE 必须恰好指定一个
+    let shared = match flags & (MAP_SHARED | MAP_PRIVATE) {
+        MAP_SHARED => true,
+        MAP_PRIVATE => false,
+        _ => return -EINVAL,
+    };
+    if flags & MAP_ANONYMOUS != 0 && shared {
+        return -EINVAL;
+    }
+    let len = match len.checked_add(PAGE_SIZE - 1) {
+        Some(len) => len / PAGE_SIZE * PAGE_SIZE,
+        None => return -ENOMEM,
+    };
+    let proc = process::current_process().expect("kernel thread cannot mmap!");
+    let mut proc = proc.lock();
+    let inode = if flags & MAP_ANONYMOUS != 0 {
+        None
+    } else {
+        match proc.files.get(fd) {
+            Some(Some(File::INode { inode, .. })) => Some(inode.clone()),
+            _ => return -EBADF,
+        }
+    };
+    let start = if flags & MAP_FIXED != 0 {
+        // 不允许覆盖用户栈以及内核所在的区域
+        if addr == 0 || addr.checked_add(len).map_or(true, |end| end > USER_STACK_OFFSET) {
+            return -EINVAL;
+        }
+        proc.vm.remove(addr, addr + len);
+        addr
+    } else {
+        // 参考地址不在用户地址空间中时忽略它
+        let hint = if addr == 0 || addr >= USER_STACK_OFFSET { USER_MMAP_OFFSET } else { addr };
+        // 从参考地址开始找不到时，再从 USER_MMAP_OFFSET 开始寻找
+        match proc.vm.find_free_area(hint, len).or_else(|| proc.vm.find_free_area(USER_MMAP_OFFSET, len)) {
+            Some(start) => start,
+            None => return -ENOMEM,
+        }
+    };
+    match inode {
+        Some(inode) => proc.vm.push(
+            start,
+            start + len,
+            prot_to_attr(prot),
+            FileBacked::new(inode, start, offset, shared),
+            None,
+        ),
+        None => proc.vm.push(start, start + len, prot_to_attr(prot), Lazy::new(), None),
+    }
+    start as isize
+}
+
+// 删除当前进程的虚拟内存空间中 [addr, addr + len) 内的全部映射
+fn sys_munmap(addr: usize, len: usize) -> isize {
+    if len == 0 || addr % PAGE_SIZE != 0 {
+        return -EINVAL;
+    }
+    let len = match len.checked_add(PAGE_SIZE - 1) {
+        Some(len) => len / PAGE_SIZE * PAGE_SIZE,
+        None => return -EINVAL,
+    };
+    if addr.checked_add(len).map_or(true, |end| end > USER_STACK_OFFSET) {
+        return -EINVAL;
+    }
+    let proc = process::current_process().expect("kernel thread cannot munmap!");
+    proc.lock().vm.remove(addr, addr + len);
+    0
+}
+
+// 将当前进程的虚拟内存空间中 [addr, addr + len) 的权限修改为 prot
+// 区间中有未被映射的部分时返回 -ENOMEM
+fn sys_mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    if addr % PAGE_SIZE != 0 {
+        return -EINVAL;
+    }
+    let len = match len.checked_add(PAGE_SIZE - 1) {
+        Some(len) => len / PAGE_SIZE * PAGE_SIZE,
+        None => return -EINVAL,
+    };
+    if len == 0 {
+        return 0;
+    }
+    if addr.checked_add(len).map_or(true, |end| end > USER_STACK_OFFSET) {
+        return -EINVAL;
+    }
+    let proc = process::current_process().expect("kernel thread cannot mprotect!");
+    if proc.lock().vm.protect(addr, addr + len, prot_to_attr(prot)) {
+        0
+    } else {
+        -ENOMEM
+    }
+}
+
+// 按照 mode 设置当前线程的系统调用跟踪
+fn sys_trace(mode: usize) -> isize {
+    match mode {
+        TRACE_OFF => process::set_trace(false, false),
+        TRACE_SELF => process::set_trace(true, false),
+        TRACE_CHILDREN => process::set_trace(false, true),
+        _ => return -EINVAL,
+    }
+    0
+}
+
+fn sys_getpid() -> isize {
+    process::current_pid().expect("kernel thread has no pid!") as isize
+}
+
+// 新线程从 entry 开始执行，通过 a0, a1 传入 arg0, arg1
+// 返回新线程的 Tid ，可以通过 wait4 等待其退出
+fn sys_thread_create(entry: usize, arg0: usize, arg1: usize) -> isize {
+    process::thread_create(entry, [arg0, arg1, 0]) as isize
+}
+
+// argv, envp 为传给用户程序的参数与环境变量，均为以空指针结尾的字符串指针数组
+// 找不到程序时返回 -ENOENT ，不是合法的可执行文件时返回 -ENOEXEC
+// 参数与环境变量的总大小超过 ARG_MAX 时返回 -E2BIG
+fn sys_exec(path: *const u8, argv: *const *const u8, envp: *const *const u8) -> isize {
+    let mut limit = ARG_MAX;
+    let (path, args, envs) = match (
+        read_user_cstr(path as usize),
+        read_user_cstr_array(argv as usize, &mut limit),
+        read_user_cstr_array(envp as usize, &mut limit),
+    ) {
+        (Ok(path), Ok(args), Ok(envs)) => (path, args, envs),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return err,
+    };
+    match process::execute(&path, args, envs) {
+        Ok(tid) => {
+            // 如果正常执行，则阻塞终端线程，等到启动的这个用户线程运行结束
+            process::wait(Some(tid), false).unwrap();
+            0
+        },
+        Err(ExecError::NotFound) => -ENOENT,
+        Err(ExecError::NotExecutable) => -ENOEXEC,
+    }
+}
+
+// pid 为 -1 表示等待任意一个子线程，否则等待 Tid 为 pid 的子线程
+// 成功时返回子线程的 Tid ，并按照 Linux 的格式将退出码写入 wstatus
+// WNOHANG 模式下子线程都还没有退出时返回 0 ；没有符合条件的子线程时返回 -ECHILD
+fn sys_wait4(pid: isize, wstatus: *mut i32, options: usize) -> isize {
+    let tid = if pid == -1 { None } else { Some(pid as usize) };
+    match process::wait(tid, options & WNOHANG != 0) {
+        Ok(Some((tid, code))) => {
+            if !wstatus.is_null() {
+                let status = ((code & 0xff) << 8) as i32;
+                if let Err(err) = write_user(wstatus as usize, &status) {
+                    return err;
+                }
+            }
+            tid as isize
+        },
+        Ok(None) => 0,
+        Err(_) => -ECHILD,
+    }
 }
\ No newline at end of file
@@ -3,12 +3,20 @@ use riscv::register::{
     time,
     sie
 };
+use crate::process::{ self, Waiter };
+use crate::interrupt::{ disable_and_store, restore };
+use alloc::collections::BinaryHeap;
+use core::cmp::Reverse;
+use spin::Mutex;
+use lazy_static::*;
 
 // 当前已触发多少次时钟中断
 pub static mut TICKS: usize = 0;
 // 触发时钟中断时间间隔
 // 数值一般约为 cpu 频率的 1% ， 防止过多占用 cpu 资源
 static TIMEBASE: u64 = 100000;
+// time 寄存器每秒增加的次数，即 QEMU virt 平台的时钟频率
+pub const CLOCK_FREQ: u64 = 10000000;
 pub fn init() {
     unsafe {
         // 初始化时钟中断触发次数
@@ -31,6 +39,61 @@ pub fn clock_set_next_event() {
 }
 
 // 获取当前时间
-fn get_cycle() -> u64 {
+pub fn get_cycle() -> u64 {
     time::read() as u64
+}
+
+// 定时器队列：记录每个睡眠线程被唤醒的时间
+pub struct Timer {
+    // 按照唤醒时间排序的小根堆
+    events: BinaryHeap<Reverse<(u64, Waiter)>>,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer { events: BinaryHeap::new() }
+    }
+    // 线程 waiter 将在 deadline 时刻被唤醒
+    pub fn add(&mut self, deadline: u64, waiter: Waiter) {
+        self.events.push(Reverse((deadline, waiter)));
+    }
+    // 取出一个唤醒时间已经不晚于 now 的线程
+    pub fn pop_expired(&mut self, now: u64) -> Option<Waiter> {
+        match self.events.peek() {
+            Some(Reverse((deadline, _))) if *deadline <= now => {
+                self.events.pop().map(|Reverse((_, waiter))| waiter)
+            },
+            _ => None,
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref TIMER: Mutex<Timer> = Mutex::new(Timer::new());
+}
+
+// 当前线程睡眠，直到 deadline 时刻才被唤醒
+// 线程状态： Running(Tid) -> Sleeping
+pub fn sleep_until(deadline: u64) {
+    // 在加入定时器队列到放弃 CPU 之间不能被时钟中断打断
+    // 否则可能在放弃 CPU 之前就被唤醒
+    let flags = disable_and_store();
+    TIMER.lock().add(deadline, process::current_waiter());
+    process::yield_now();
+    restore(flags);
+}
+
+// 在时钟中断中调用，唤醒所有已经到达唤醒时间的线程
+// 线程状态： Sleeping -> Ready
+pub fn wake_up_sleepers() {
+    let now = get_cycle();
+    loop {
+        // 注意在唤醒线程之前释放锁
+        let waiter = TIMER.lock().pop_expired(now);
+        match waiter {
+            // 已经退出的线程不会被唤醒
+            Some(waiter) => { process::wake_up(waiter); },
+            None => break,
+        }
+    }
 }
\ No newline at end of file
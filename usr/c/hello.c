@@ -0,0 +1,20 @@
+// 使用 musl 静态链接的 C 程序，用来测试内核对 Linux 系统调用的兼容性
+#include <stdio.h>
+#include <sys/utsname.h>
+#include <time.h>
+
+int main(int argc, char *argv[]) {
+    printf("Hello world from C!\n");
+    for (int i = 0; i < argc; i++) {
+        printf("argv[%d] = %s\n", i, argv[i]);
+    }
+    struct utsname uts;
+    if (uname(&uts) == 0) {
+        printf("%s %s %s\n", uts.sysname, uts.release, uts.machine);
+    }
+    struct timespec ts;
+    if (clock_gettime(CLOCK_MONOTONIC, &ts) == 0) {
+        printf("uptime: %ld.%09ld s\n", (long)ts.tv_sec, ts.tv_nsec);
+    }
+    return 0;
+}
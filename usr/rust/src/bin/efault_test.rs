@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::io::STDOUT;
+use user::syscall::{
+    sys_write,
+    sys_read,
+    sys_open,
+    sys_close,
+    sys_mmap,
+    sys_munmap,
+    Error,
+    PROT_READ,
+    MAP_PRIVATE,
+    MAP_ANONYMOUS,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+// 向系统调用传入不合法的地址，内核应当返回 EFAULT 而不是崩溃
+#[no_mangle]
+pub fn main() -> usize {
+    // 未被映射的地址
+    assert_eq!(Error::check(sys_write(STDOUT, 0x10 as *const u8, 8)), Err(Error::EFAULT));
+    // 内核的地址
+    assert_eq!(Error::check(sys_write(STDOUT, 0xffffffffc0200000 as *const u8, 8)), Err(Error::EFAULT));
+    assert_eq!(Error::check(sys_open(0x10 as *const u8, 0)), Err(Error::EFAULT));
+    // 只读的地址不能用来存放读入的内容
+    let addr = sys_mmap(0, PAGE_SIZE, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, 0, 0);
+    assert!(addr > 0, "mmap failed!");
+    let fd = sys_open("rust/hello_world\0".as_ptr(), 0);
+    assert!(fd >= 0, "open failed!");
+    assert_eq!(Error::check(sys_read(fd as usize, addr as *mut u8, 16)), Err(Error::EFAULT));
+    sys_close(fd as usize);
+    assert_eq!(sys_munmap(addr as usize, PAGE_SIZE), 0);
+    println!("efault test passed");
+    0
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_open,
+    sys_close,
+    sys_mmap,
+    sys_munmap,
+    PROT_READ,
+    PROT_WRITE,
+    MAP_PRIVATE,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+// 私有地映射一个用户程序文件，检查其开头的 ELF 魔数
+// 对私有映射的修改不会写回文件，重新映射后看到的仍是原来的内容
+fn map_and_check(fd: usize) {
+    let addr = sys_mmap(0, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    assert!(addr > 0, "mmap failed!");
+    let buf = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, PAGE_SIZE) };
+    assert_eq!(&buf[0..4], b"\x7fELF");
+    buf[0] = 0;
+    assert_eq!(sys_munmap(addr as usize, PAGE_SIZE), 0);
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let fd = sys_open("rust/hello_world\0".as_ptr(), 0);
+    assert!(fd >= 0, "open failed!");
+    map_and_check(fd as usize);
+    map_and_check(fd as usize);
+    sys_close(fd as usize);
+    println!("file mmap test passed");
+    0
+}
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{ sys_fork, sys_wait4 };
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 父子线程各自拥有一份 value
+    let mut value = 1;
+    let tid = sys_fork();
+    if tid == 0 {
+        value += 10;
+        println!("I am child, value = {}", value);
+        return 7;
+    }
+    value += 100;
+    println!("I am parent, fork a child {}, value = {}", tid, value);
+    // 等待子线程退出并取得其退出码
+    let mut wstatus = 0i32;
+    let ret = sys_wait4(tid as isize, &mut wstatus, 0);
+    println!("child {} exited, exit code = {}", ret, (wstatus >> 8) & 0xff);
+    0
+}
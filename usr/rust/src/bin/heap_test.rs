@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user::syscall::sys_brk;
+
+// 分配远超初始堆大小的内存，用户堆应当通过 sys_brk 自动扩大
+#[no_mangle]
+pub fn main() -> usize {
+    let start = sys_brk(0);
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..0x10000 {
+        v.push(i);
+    }
+    let sum: usize = v.iter().sum();
+    assert_eq!(sum, 0x10000 * (0x10000 - 1) / 2);
+    println!("heap grows from {:#x} to {:#x}", start, sys_brk(0));
+    println!("heap test passed");
+    0
+}
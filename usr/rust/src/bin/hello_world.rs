@@ -6,10 +6,16 @@ extern crate alloc;
 #[macro_use]
 extern crate user;
 
+use user::env;
+
 #[no_mangle]
 pub fn main() -> usize {
     for _ in 0..10 {
         println!("Hello world! from user mode program!");
     }
+    // 输出传入的全部参数
+    for (i, arg) in env::args().enumerate() {
+        println!("argv[{}] = {}", i, arg);
+    }
     0
-}
\ No newline at end of file
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_mmap,
+    sys_munmap,
+    PROT_READ,
+    PROT_WRITE,
+    MAP_PRIVATE,
+    MAP_ANONYMOUS,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+pub fn main() -> usize {
+    // 申请 4 页可读写的匿名内存
+    let len = 4 * PAGE_SIZE;
+    let addr = sys_mmap(0, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, 0, 0);
+    assert!(addr > 0, "mmap failed!");
+    let addr = addr as usize;
+    println!("mmap {} bytes at {:#x}", len, addr);
+    let buf = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) };
+    for i in 0..len {
+        buf[i] = i as u8;
+    }
+    // 删除中间的两页，原区域被拆分为首尾两段
+    assert_eq!(sys_munmap(addr + PAGE_SIZE, 2 * PAGE_SIZE), 0);
+    // 首尾两页的内容不受影响
+    for i in (0..PAGE_SIZE).chain(3 * PAGE_SIZE..len) {
+        assert_eq!(buf[i], i as u8);
+    }
+    assert_eq!(sys_munmap(addr, len), 0);
+    println!("mmap test passed");
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+#![feature(asm)]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_mmap,
+    sys_munmap,
+    sys_mprotect,
+    PROT_READ,
+    PROT_WRITE,
+    PROT_EXEC,
+    MAP_PRIVATE,
+    MAP_ANONYMOUS,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+// li a0, 42
+// ret
+const CODE: [u32; 2] = [0x02a00513, 0x00008067];
+
+#[no_mangle]
+pub fn main() -> usize {
+    let addr = sys_mmap(0, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, 0, 0);
+    assert!(addr > 0, "mmap failed!");
+    let addr = addr as usize;
+    // 先写入一段代码，再将第一页改为可读可执行，第二页仍然可读写
+    let code = unsafe { core::slice::from_raw_parts_mut(addr as *mut u32, CODE.len()) };
+    code.copy_from_slice(&CODE);
+    assert_eq!(sys_mprotect(addr, PAGE_SIZE, PROT_READ | PROT_EXEC), 0);
+    unsafe { asm!("fence.i" :::: "volatile"); }
+    let f: extern "C" fn() -> usize = unsafe { core::mem::transmute(addr) };
+    assert_eq!(f(), 42);
+    unsafe { *((addr + PAGE_SIZE) as *mut usize) = 1; }
+    // 区间中有未被映射的部分时失败
+    assert_eq!(sys_munmap(addr + PAGE_SIZE, PAGE_SIZE), 0);
+    assert!(sys_mprotect(addr, 2 * PAGE_SIZE, PROT_READ) < 0);
+    assert_eq!(sys_munmap(addr, PAGE_SIZE), 0);
+    println!("mprotect test passed");
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::io::STDIN;
+use user::syscall::{ sys_read, sys_fcntl, F_GETFL, F_SETFL, O_NONBLOCK };
+use user::thread;
+
+// 将标准输入设为不阻塞，在等待输入的同时做些别的事情
+// 读到一行后恢复为阻塞模式
+#[no_mangle]
+pub fn main() -> usize {
+    let flags = sys_fcntl(STDIN, F_GETFL, 0) as usize;
+    sys_fcntl(STDIN, F_SETFL, flags | O_NONBLOCK);
+    println!("type something and press enter:");
+    let mut line = [0u8; 64];
+    let mut len = 0;
+    let mut polls = 0;
+    while len < line.len() {
+        let ret = sys_read(STDIN, line[len..].as_mut_ptr(), line.len() - len);
+        if ret < 0 {
+            // 还没有输入，过一会儿再来
+            polls += 1;
+            thread::sleep(10);
+            continue;
+        }
+        len += ret as usize;
+        if line[..len].contains(&b'\n') {
+            break;
+        }
+    }
+    sys_fcntl(STDIN, F_SETFL, flags);
+    println!("read {} bytes after polling {} times", len, polls);
+    0
+}
@@ -0,0 +1,30 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{ sys_gettid, sys_wait4 };
+use user::thread;
+
+// 睡眠时间不同的线程应当按照睡眠时间从短到长的顺序醒来
+fn sleeper(ms: usize) -> usize {
+    thread::sleep(ms);
+    println!("thread {} woke up after {} ms", sys_gettid(), ms);
+    0
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut tids = [0i64; 3];
+    let times = [300, 100, 200];
+    for i in 0..tids.len() {
+        tids[i] = thread::spawn(sleeper, times[i]);
+    }
+    for tid in tids.iter() {
+        let mut wstatus = 0i32;
+        sys_wait4(*tid as isize, &mut wstatus, 0);
+    }
+    println!("sleep test passed");
+    0
+}
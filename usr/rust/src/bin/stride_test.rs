@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{ sys_fork, sys_set_priority, sys_wait4, sys_exit };
+
+const CHILDREN: usize = 5;
+const WORK: usize = 2000000;
+
+// 需要内核使用 StrideScheduler
+// 各子线程的工作量相同，优先级越高的应当越早完成
+#[no_mangle]
+pub fn main() -> usize {
+    for i in 0..CHILDREN {
+        if sys_fork() == 0 {
+            let priority = i + 1;
+            sys_set_priority(priority);
+            let mut x = 0usize;
+            for j in 0..WORK {
+                x = x.wrapping_add(j);
+                // 防止循环被优化掉
+                unsafe { core::ptr::read_volatile(&x); }
+            }
+            println!("child with priority {} finished", priority);
+            sys_exit(0);
+        }
+    }
+    for _ in 0..CHILDREN {
+        sys_wait4(-1, core::ptr::null_mut(), 0);
+    }
+    0
+}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use user::syscall::{
+    sys_mmap,
+    sys_munmap,
+    PROT_READ,
+    PROT_WRITE,
+    MAP_PRIVATE,
+    MAP_ANONYMOUS,
+};
+
+const PAGE_SIZE: usize = 4096;
+// 超过内核允许驻留在内存中的可换出页数，迫使内核将一部分页换出到交换区
+const PAGES: usize = 1536;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let len = PAGES * PAGE_SIZE;
+    let addr = sys_mmap(0, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, 0, 0);
+    assert!(addr > 0, "mmap failed!");
+    let addr = addr as usize;
+    println!("mmap {} pages at {:#x}", PAGES, addr);
+    // 在每一页的开头和结尾写入页号
+    for i in 0..PAGES {
+        let page = unsafe { core::slice::from_raw_parts_mut((addr + i * PAGE_SIZE) as *mut usize, PAGE_SIZE / 8) };
+        page[0] = i;
+        page[PAGE_SIZE / 8 - 1] = !i;
+    }
+    // 按照相反的顺序读回，此时前面的页大多已被换出，需要再换入
+    for i in (0..PAGES).rev() {
+        let page = unsafe { core::slice::from_raw_parts((addr + i * PAGE_SIZE) as *const usize, PAGE_SIZE / 8) };
+        assert_eq!(page[0], i);
+        assert_eq!(page[PAGE_SIZE / 8 - 1], !i);
+    }
+    assert_eq!(sys_munmap(addr, len), 0);
+    println!("swap test passed");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user;
+
+use core::sync::atomic::{ AtomicUsize, Ordering };
+use user::syscall::{ sys_getpid, sys_gettid, sys_wait4 };
+use user::thread;
+
+// 同一进程中的各线程共享虚拟内存空间，因此共享这个变量
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn worker(arg: usize) -> usize {
+    println!("thread {} of process {} got arg {}", sys_gettid(), sys_getpid(), arg);
+    for _ in 0..100 {
+        COUNTER.fetch_add(1, Ordering::SeqCst);
+    }
+    arg * 10
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut tids = [0i64; 4];
+    for i in 0..tids.len() {
+        tids[i] = thread::spawn(worker, i);
+    }
+    for tid in tids.iter() {
+        let mut wstatus = 0i32;
+        sys_wait4(*tid as isize, &mut wstatus, 0);
+        println!("thread {} exited, exit code = {}", tid, (wstatus >> 8) & 0xff);
+    }
+    println!("counter = {}", COUNTER.load(Ordering::SeqCst));
+    0
+}
@@ -11,8 +11,30 @@ const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 
 use user::io::getc;
-use user::syscall::sys_exec;
-use alloc::string::String;
+use user::syscall::{ sys_exec, sys_trace, TRACE_OFF, TRACE_CHILDREN, Error };
+use alloc::{ string::String, vec::Vec };
+use core::ptr::null;
+
+// 执行程序并等待其结束，args[0] 为程序名
+fn execute(args: &[String]) {
+    let mut argv: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+    argv.push(null());
+    // 第一个参数是程序名，不含路径时到 rust 目录下寻找
+    let mut path = String::new();
+    if !args[0].contains('/') {
+        path.push_str("rust/");
+    }
+    path.push_str(&args[0]);
+    println!("searching for program {}", path);
+    // 使用系统调用执行程序
+    match Error::check(sys_exec(path.as_ptr(), argv.as_ptr(), null())) {
+        Ok(_) => {},
+        Err(Error::ENOENT) => println!("command not found!"),
+        Err(Error::ENOEXEC) => println!("not an executable file!"),
+        Err(Error::E2BIG) => println!("argument list too long!"),
+        Err(err) => println!("failed to execute: {:?}", err),
+    }
+}
 
 #[no_mangle]
 pub fn main() {
@@ -26,14 +48,33 @@ pub fn main() {
            LF | CR => {
                // 如果遇到回车或换行
                println!("");
-               if !line.is_empty() {
-                   line.push('\0');
-                   println!("searching for program {}", line);
-                   // 使用系统调用执行程序
-                   sys_exec(line.as_ptr());
-                   // 清空本行内容
-                   line.clear();
+               if !line.trim().is_empty() {
+                   // 按空白字符切分为参数，每个参数都以 \0 结尾
+                   let mut args: Vec<String> = line
+                       .split_whitespace()
+                       .map(|arg| {
+                           let mut arg = String::from(arg);
+                           arg.push('\0');
+                           arg
+                       })
+                       .collect();
+                   // 内建命令 strace ：跟踪所执行程序的系统调用
+                   // 只跟踪 exec 启动的程序及其创建的线程，不跟踪 shell 自身
+                   if args[0] == "strace\0" {
+                       args.remove(0);
+                       if args.is_empty() {
+                           println!("usage: strace <program> [args...]");
+                       } else {
+                           sys_trace(TRACE_CHILDREN);
+                           execute(&args);
+                           sys_trace(TRACE_OFF);
+                       }
+                   } else {
+                       execute(&args);
+                   }
                }
+               // 清空本行内容
+               line.clear();
                print!(">> ");
            },
            _ => {
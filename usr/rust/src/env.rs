@@ -0,0 +1,48 @@
+use core::{ slice, str };
+
+// 程序启动时由内核传入的参数与环境变量
+// 均为以空指针结尾的字符串指针数组
+static mut ARGV: *const *const u8 = core::ptr::null();
+static mut ENVP: *const *const u8 = core::ptr::null();
+
+pub(crate) fn init(argv: *const *const u8, envp: *const *const u8) {
+    unsafe {
+        ARGV = argv;
+        ENVP = envp;
+    }
+}
+
+// 遍历字符串指针数组的迭代器
+pub struct Args {
+    ptr: *const *const u8,
+}
+
+impl Iterator for Args {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<&'static str> {
+        if self.ptr.is_null() {
+            return None;
+        }
+        unsafe {
+            let s = *self.ptr;
+            if s.is_null() {
+                return None;
+            }
+            self.ptr = self.ptr.add(1);
+            // 使用迭代器获得字符串长度
+            let len = (0usize..).find(|&i| *s.add(i) == 0).unwrap();
+            Some(str::from_utf8_unchecked(slice::from_raw_parts(s, len)))
+        }
+    }
+}
+
+// 程序的参数，第一个通常为程序名
+pub fn args() -> Args {
+    Args { ptr: unsafe { ARGV } }
+}
+
+// 程序的环境变量，每一项形如 KEY=VALUE
+pub fn vars() -> Args {
+    Args { ptr: unsafe { ENVP } }
+}
@@ -0,0 +1,42 @@
+use buddy_system_allocator::LockedHeap;
+use core::alloc::{ GlobalAlloc, Layout };
+use core::ptr::NonNull;
+use crate::syscall::sys_sbrk;
+
+// 每次扩大用户堆时至少扩大的字节数
+const HEAP_GROW_SIZE: usize = 0x4000;
+
+// 用于 U Mode 中动态内存分配的用户堆
+// 一开始为空，分配失败时通过 sys_sbrk 向内核申请更多空间，再交给伙伴系统管理
+pub struct GrowingHeap(LockedHeap);
+
+impl GrowingHeap {
+    pub const fn empty() -> Self {
+        GrowingHeap(LockedHeap::empty())
+    }
+}
+
+unsafe impl GlobalAlloc for GrowingHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        // 伙伴系统按 2 的幂对齐分配
+        // 新增一段长为 2 * size 的空间，就能保证其中包含一个大小为 size 且对齐的块
+        let size = layout.size().max(layout.align()).next_power_of_two();
+        let increment = (2 * size).max(HEAP_GROW_SIZE);
+        let start = sys_sbrk(increment);
+        if start < 0 {
+            return core::ptr::null_mut();
+        }
+        heap.add_to_heap(start as usize, start as usize + increment);
+        match heap.alloc(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(_) => core::ptr::null_mut(),
+        }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout);
+    }
+}
@@ -7,7 +7,29 @@ use core::fmt::{ self, Write };
 // 标准输出 stdout fd = 1
 // 标准错误输出 stderr fd = 2
 pub const STDIN: usize = 0;
-struct Stdout;
+pub const STDOUT: usize = 1;
+
+// 格式化输出时使用的缓冲区大小
+const BUFFER_SIZE: usize = 1024;
+
+// 带缓冲区的标准输出
+// 格式化输出的各部分先放在缓冲区中，缓冲区满或者输出结束时才通过一次 sys_write 输出
+struct Stdout {
+    buf: [u8; BUFFER_SIZE],
+    len: usize,
+}
+
+impl Stdout {
+    fn new() -> Self {
+        Stdout { buf: [0; BUFFER_SIZE], len: 0 }
+    }
+    fn flush(&mut self) {
+        if self.len > 0 {
+            sys_write(STDOUT, self.buf.as_ptr(), self.len);
+            self.len = 0;
+        }
+    }
+}
 
 // 调用 sys_read 从标准输入读入一个字符
 pub fn getc() -> u8 {
@@ -18,25 +40,34 @@ pub fn getc() -> u8 {
 
 // 输出一个字符
 pub fn putchar(ch: char) {
-    sys_write(ch as u8);
+    puts(ch.encode_utf8(&mut [0; 4]));
 }
 
 // 输出一个字符串
 pub fn puts(s: &str) {
-    for ch in s.chars() {
-        putchar(ch);
-    }
+    sys_write(STDOUT, s.as_ptr(), s.len());
 }
 
 impl fmt::Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        puts(s);
+        let mut s = s.as_bytes();
+        while !s.is_empty() {
+            if self.len == BUFFER_SIZE {
+                self.flush();
+            }
+            let n = s.len().min(BUFFER_SIZE - self.len);
+            self.buf[self.len..self.len + n].copy_from_slice(&s[..n]);
+            self.len += n;
+            s = &s[n..];
+        }
         Ok(())
     }
 }
 
 pub fn _print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    let mut stdout = Stdout::new();
+    stdout.write_fmt(args).unwrap();
+    stdout.flush();
 }
 
 #[macro_export]
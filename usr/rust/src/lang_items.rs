@@ -8,16 +8,6 @@ fn main() -> usize {
     panic!("No main() linked");
 }
 
-use crate::DYNAMIC_ALLOCATOR;
-// 初始化用户堆，用于U Mode中动态内存分配
-fn init_heap() {
-    const HEAP_SIZE: usize = 0x1000;
-    static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
-    unsafe {
-        DYNAMIC_ALLOCATOR.lock().init(HEAP.as_ptr() as usize, HEAP_SIZE);
-    }
-}
-
 #[panic_handler]
 fn panic(_info: &PanicInfo) -> ! {
     let location = _info.location().unwrap();
@@ -32,10 +22,12 @@ fn panic(_info: &PanicInfo) -> ! {
 }
 
 // 这里是程序入口
+// 内核通过 a0, a1, a2 传入 argc, argv, envp ，保存下来供 env 模块使用
+// 用户堆在第一次分配时通过 sys_brk 按需扩大，见 heap 模块
 // 调用 main 函数，并利用 sys_exit 系统调用退出
 #[no_mangle]
-pub extern "C" fn _start(_args: isize, _argv: *const u8) -> ! {
-    init_heap();
+pub extern "C" fn _start(_argc: isize, argv: *const *const u8, envp: *const *const u8) -> ! {
+    crate::env::init(argv, envp);
     sys_exit(main())
 }
 
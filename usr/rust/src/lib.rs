@@ -11,8 +11,11 @@ pub mod io;
 
 pub mod syscall;
 pub mod lang_items;
+pub mod env;
+pub mod thread;
+pub mod heap;
 
-use buddy_system_allocator::LockedHeap;
+use heap::GrowingHeap;
 
 #[global_allocator]
-static DYNAMIC_ALLOCATOR: LockedHeap = LockedHeap::empty();
\ No newline at end of file
+static DYNAMIC_ALLOCATOR: GrowingHeap = GrowingHeap::empty();
\ No newline at end of file
@@ -1,10 +1,88 @@
 enum SyscallId {
+    Fcntl = 25,
+    OpenAt = 56,
+    Close = 57,
     Read = 63,
     Write = 64,
     Exit = 93,
+    Nanosleep = 101,
+    GetPid = 172,
+    GetTid = 178,
+    Brk = 214,
+    Munmap = 215,
+    Fork = 220,
     Exec = 221,
+    Mmap = 222,
+    Mprotect = 226,
+    Wait4 = 260,
+    // 非 Linux 标准
+    ThreadCreate = 1000,
+    Trace = 1001,
+    SetPriority = 1002,
 }
 
+// 系统调用失败时返回负的错误码，与 Linux 相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // 文件不存在
+    ENOENT,
+    // 参数与环境变量过长
+    E2BIG,
+    // 输入输出错误
+    EIO,
+    // 不是合法的可执行文件
+    ENOEXEC,
+    // 文件描述符不合法
+    EBADF,
+    // 没有符合条件的子线程
+    ECHILD,
+    // 暂时无法完成，需要稍后再试
+    EAGAIN,
+    // 内存不足或地址区间未被映射
+    ENOMEM,
+    // 地址不合法
+    EFAULT,
+    // 参数不合法
+    EINVAL,
+    // 不是终端设备
+    ENOTTY,
+    // 系统调用不存在
+    ENOSYS,
+    // 其他错误码
+    Unknown(i64),
+}
+
+impl Error {
+    pub fn from_errno(errno: i64) -> Self {
+        match errno {
+            2 => Error::ENOENT,
+            7 => Error::E2BIG,
+            5 => Error::EIO,
+            8 => Error::ENOEXEC,
+            9 => Error::EBADF,
+            10 => Error::ECHILD,
+            11 => Error::EAGAIN,
+            12 => Error::ENOMEM,
+            14 => Error::EFAULT,
+            22 => Error::EINVAL,
+            25 => Error::ENOTTY,
+            38 => Error::ENOSYS,
+            errno => Error::Unknown(errno),
+        }
+    }
+    // 将系统调用的返回值转化为 Result ，负数表示失败
+    pub fn check(ret: i64) -> Result<usize, Error> {
+        if ret < 0 {
+            Err(Error::from_errno(-ret))
+        } else {
+            Ok(ret as usize)
+        }
+    }
+}
+
+// sys_wait4 的选项：子线程都还没有退出时不阻塞，直接返回 0
+pub const WNOHANG: usize = 1;
+
 #[inline(always)]
 fn sys_call(
     syscall_id: SyscallId,
@@ -12,6 +90,8 @@ fn sys_call(
     arg1: usize,
     arg2: usize,
     arg3: usize,
+    arg4: usize,
+    arg5: usize,
 ) -> i64 {
     let id = syscall_id as usize;
     let mut ret: i64;
@@ -19,7 +99,7 @@ fn sys_call(
         asm!(
             "ecall"
             : "={x10}"(ret)
-            : "{x17}"(id), "{x10}"(arg0), "{x11}"(arg1), "{x12}"(arg2), "{x13}"(arg3)
+            : "{x17}"(id), "{x10}"(arg0), "{x11}"(arg1), "{x12}"(arg2), "{x13}"(arg3), "{x14}"(arg4), "{x15}"(arg5)
             : "memory"
             : "volatile"
         );
@@ -27,20 +107,149 @@ fn sys_call(
     ret
 }
 
-pub fn sys_read(fd: usize, base: *const u8, len: usize) -> i64 {
-    sys_call(SyscallId::Read, fd, base as usize, len, 0)
+// sys_openat 的 dirfd 参数：相对于当前目录
+const AT_FDCWD: isize = -100;
+
+// 打开路径为 path 的文件，path 为以 \0 结尾的字符串，返回其文件描述符
+pub fn sys_open(path: *const u8, flags: usize) -> i64 {
+    sys_call(SyscallId::OpenAt, AT_FDCWD as usize, path as usize, flags, 0, 0, 0)
+}
+
+pub fn sys_close(fd: usize) -> i64 {
+    sys_call(SyscallId::Close, fd, 0, 0, 0, 0, 0)
 }
 
-pub fn sys_write(ch: u8) -> i64 {
-    sys_call(SyscallId::Write, ch as usize, 0, 0, 0)
+// sys_fcntl 的 cmd 参数
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+// 文件的标志：读写时不阻塞
+pub const O_NONBLOCK: usize = 0x800;
+
+// 目前只支持通过 F_GETFL, F_SETFL 查询与修改标准输入的 O_NONBLOCK 标志
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> i64 {
+    sys_call(SyscallId::Fcntl, fd, cmd, arg, 0, 0, 0)
+}
+
+// 从文件描述符 fd 对应的文件读入至多 len 字节，返回读入的字节数
+// 标准输入被设为 O_NONBLOCK 时，若还没有输入则返回 -EAGAIN
+pub fn sys_read(fd: usize, base: *mut u8, len: usize) -> i64 {
+    sys_call(SyscallId::Read, fd, base as usize, len, 0, 0, 0)
+}
+
+// 将从 base 开始的 len 字节写入文件描述符 fd 对应的文件，返回写入的字节数
+pub fn sys_write(fd: usize, base: *const u8, len: usize) -> i64 {
+    sys_call(SyscallId::Write, fd, base as usize, len, 0, 0, 0)
 }
 
 pub fn sys_exit(code: usize) -> ! {
-    sys_call(SyscallId::Exit, code, 0, 0, 0);
+    sys_call(SyscallId::Exit, code, 0, 0, 0, 0, 0);
     loop {}
 }
 
+// 与 Linux 的 struct timespec 布局相同
+#[repr(C)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+// 当前线程睡眠 req 指定的时长
+pub fn sys_nanosleep(req: &TimeSpec) -> i64 {
+    sys_call(SyscallId::Nanosleep, req as *const TimeSpec as usize, 0, 0, 0, 0, 0)
+}
+
+// 设置当前线程的优先级，优先级越大分配到的 CPU 时间越多
+pub fn sys_set_priority(priority: usize) -> i64 {
+    sys_call(SyscallId::SetPriority, priority, 0, 0, 0, 0, 0)
+}
+
+// 当前进程的进程号
+pub fn sys_getpid() -> i64 {
+    sys_call(SyscallId::GetPid, 0, 0, 0, 0, 0, 0)
+}
+
+// 当前线程的 Tid
+pub fn sys_gettid() -> i64 {
+    sys_call(SyscallId::GetTid, 0, 0, 0, 0, 0, 0)
+}
+
+// 将用户堆的结束地址调整为 addr ，返回调整后的结束地址
+// 调整失败时结束地址不变，addr 为 0 可用于查询当前的结束地址
+pub fn sys_brk(addr: usize) -> i64 {
+    sys_call(SyscallId::Brk, addr, 0, 0, 0, 0, 0)
+}
+
+// 将用户堆扩大 increment 字节，返回扩大前的结束地址，即新增空间的起始地址
+// 失败时返回 -1
+pub fn sys_sbrk(increment: usize) -> i64 {
+    let old = sys_brk(0);
+    if sys_brk(old as usize + increment) == old + increment as i64 {
+        old
+    } else {
+        -1
+    }
+}
+
+// sys_mmap 的 prot 参数：映射区域的权限
+pub const PROT_NONE: usize = 0x0;
+pub const PROT_READ: usize = 0x1;
+pub const PROT_WRITE: usize = 0x2;
+pub const PROT_EXEC: usize = 0x4;
+// sys_mmap 的 flags 参数
+pub const MAP_SHARED: usize = 0x1;
+pub const MAP_PRIVATE: usize = 0x2;
+pub const MAP_FIXED: usize = 0x10;
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+// 新建一段长为 len 字节的映射，成功时返回其起始地址，失败时返回负数
+// 匿名映射只支持私有映射，即 MAP_PRIVATE | MAP_ANONYMOUS ，此时 fd 与 offset 被忽略
+// 否则映射文件 fd 中从 offset 开始的内容，MAP_SHARED 的修改会写回文件，MAP_PRIVATE 的修改只对本进程可见
+pub fn sys_mmap(addr: usize, len: usize, prot: usize, flags: usize, fd: usize, offset: usize) -> i64 {
+    sys_call(SyscallId::Mmap, addr, len, prot, flags, fd, offset)
+}
+
+// 删除 [addr, addr + len) 内的全部映射
+pub fn sys_munmap(addr: usize, len: usize) -> i64 {
+    sys_call(SyscallId::Munmap, addr, len, 0, 0, 0, 0)
+}
+
+// 将 [addr, addr + len) 的权限修改为 prot ，其中有未被映射的部分时返回负数
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> i64 {
+    sys_call(SyscallId::Mprotect, addr, len, prot, 0, 0, 0)
+}
+
+// 父线程返回子线程的 Tid ，子线程返回 0
+pub fn sys_fork() -> i64 {
+    sys_call(SyscallId::Fork, 0, 0, 0, 0, 0, 0)
+}
+
 // 传入路径字符串的地址
-pub fn sys_exec(path: *const u8) {
-    sys_call(SyscallId::Exec, path as usize, 0, 0, 0);
-}
\ No newline at end of file
+// 以及参数与环境变量，均为以空指针结尾的字符串指针数组
+// 等到程序运行结束后返回 0 ；找不到程序时返回 -ENOENT ，不是合法的可执行文件时返回 -ENOEXEC
+pub fn sys_exec(path: *const u8, argv: *const *const u8, envp: *const *const u8) -> i64 {
+    sys_call(SyscallId::Exec, path as usize, argv as usize, envp as usize, 0, 0, 0)
+}
+// 等待子线程退出，pid 为 -1 表示任意一个子线程
+// 返回子线程的 Tid ，其退出码按照 Linux 的格式写入 wstatus ，即 (code & 0xff) << 8
+pub fn sys_wait4(pid: isize, wstatus: *mut i32, options: usize) -> i64 {
+    sys_call(SyscallId::Wait4, pid as usize, wstatus as usize, options, 0, 0, 0)
+}
+
+// 在当前进程中新建一个线程，它从 entry 开始执行，并通过 a0, a1 传入 arg0, arg1
+// 返回新线程的 Tid
+pub fn sys_thread_create(entry: usize, arg0: usize, arg1: usize) -> i64 {
+    sys_call(SyscallId::ThreadCreate, entry, arg0, arg1, 0, 0, 0)
+}
+
+// sys_trace 的参数
+// 关闭跟踪
+pub const TRACE_OFF: usize = 0;
+// 跟踪当前线程，以及它此后创建的线程
+pub const TRACE_SELF: usize = 1;
+// 只跟踪当前线程此后创建的线程，不跟踪当前线程自身
+pub const TRACE_CHILDREN: usize = 2;
+
+// 按照 mode 开启或关闭系统调用跟踪
+pub fn sys_trace(mode: usize) -> i64 {
+    sys_call(SyscallId::Trace, mode, 0, 0, 0, 0, 0)
+}
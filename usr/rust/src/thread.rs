@@ -0,0 +1,23 @@
+use crate::syscall::{ sys_exit, sys_thread_create, sys_nanosleep, TimeSpec };
+
+// 新线程的入口
+// 调用 f(arg) 后以其返回值作为退出码退出
+extern "C" fn thread_entry(f: usize, arg: usize) -> ! {
+    let f: fn(usize) -> usize = unsafe { core::mem::transmute(f) };
+    sys_exit(f(arg))
+}
+
+// 在当前进程中新建一个线程执行 f(arg)
+// 返回新线程的 Tid ，可以通过 sys_wait4 等待其退出并取得 f 的返回值
+pub fn spawn(f: fn(usize) -> usize, arg: usize) -> i64 {
+    sys_thread_create(thread_entry as usize, f as usize, arg)
+}
+
+// 当前线程睡眠 ms 毫秒
+pub fn sleep(ms: usize) {
+    let req = TimeSpec {
+        sec: ms / 1000,
+        nsec: ms % 1000 * 1000000,
+    };
+    sys_nanosleep(&req);
+}